@@ -0,0 +1,114 @@
+//! A global allocator wrapper which keeps track of the number of allocations
+//! and the amount of memory in use. Enabled by the `track_alloc` feature.
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static FREES: AtomicUsize = AtomicUsize::new(0);
+static CURRENT_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TrackingAllocator<A> {
+    inner: A,
+}
+
+impl<A> TrackingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+fn add_bytes(size: usize) {
+    let current = CURRENT_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_BYTES.fetch_max(current, Ordering::Relaxed);
+}
+
+fn sub_bytes(size: usize) {
+    CURRENT_BYTES.fetch_sub(size, Ordering::Relaxed);
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for TrackingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            add_bytes(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            add_bytes(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        FREES.fetch_add(1, Ordering::Relaxed);
+        sub_bytes(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            if new_size > layout.size() {
+                add_bytes(new_size - layout.size());
+            } else {
+                sub_bytes(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// Allocation figures of a script run. Byte counts are of the whole module,
+/// including memory which was already in use before the run started.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AllocStats {
+    /// Number of allocations made during the run.
+    pub allocations: usize,
+    /// Number of deallocations made during the run.
+    pub frees: usize,
+    /// Bytes in use when the run started.
+    pub start_bytes: usize,
+    /// Bytes in use when the run finished.
+    pub end_bytes: usize,
+    /// Highest number of bytes in use during the run.
+    pub peak_bytes: usize,
+}
+
+/// Records the counters at the start of a run.
+pub struct AllocSnapshot {
+    allocations: usize,
+    frees: usize,
+    bytes: usize,
+}
+
+impl AllocSnapshot {
+    /// Takes a snapshot and resets the peak to the current usage.
+    pub fn start() -> Self {
+        let bytes = CURRENT_BYTES.load(Ordering::Relaxed);
+        PEAK_BYTES.store(bytes, Ordering::Relaxed);
+        Self {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed),
+            frees: FREES.load(Ordering::Relaxed),
+            bytes,
+        }
+    }
+
+    pub fn finish(&self) -> AllocStats {
+        AllocStats {
+            allocations: ALLOCATIONS.load(Ordering::Relaxed) - self.allocations,
+            frees: FREES.load(Ordering::Relaxed) - self.frees,
+            start_bytes: self.bytes,
+            end_bytes: CURRENT_BYTES.load(Ordering::Relaxed),
+            peak_bytes: PEAK_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::scripting::expr_stmt_positions;
+use rhai::debugger::DebuggerEvent;
+use rhai::{ASTNode, GlobalRuntimeState, Position, Stmt, AST};
+use std::collections::{BTreeMap, HashSet};
+
+/// Execution counts of a single line.
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineCoverage {
+    /// 1-based line number.
+    pub line: usize,
+    /// Number of times a statement starting on this line was executed. For
+    /// the line where a function body starts, this includes the calls to it.
+    pub hits: u64,
+    /// Operations counted while evaluating the code on this line.
+    pub operations: u64,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Coverage {
+    /// All lines containing statements, including those never executed
+    /// (with zero `hits`), sorted by line number.
+    pub lines: Vec<LineCoverage>,
+}
+
+pub struct CoverageRecorder {
+    /// Positions of statements, which the debugger reports as statements.
+    stmt_positions: HashSet<Position>,
+    expr_stmt_positions: HashSet<Position>,
+    lines: BTreeMap<usize, LineCoverage>,
+    last_line: Option<usize>,
+    last_ops: u64,
+}
+
+impl CoverageRecorder {
+    pub fn new(ast: &AST) -> Self {
+        let mut stmt_positions = HashSet::new();
+        ast.walk(&mut |path: &[ASTNode]| {
+            match path.last() {
+                Some(ASTNode::Stmt(Stmt::Expr(..)))
+                | Some(ASTNode::Stmt(Stmt::Block(..)))
+                | Some(ASTNode::Stmt(Stmt::Noop(..))) => {}
+                Some(ASTNode::Stmt(stmt)) => {
+                    stmt_positions.insert(stmt.position());
+                }
+                _ => {}
+            }
+            true
+        });
+        let expr_stmt_positions = expr_stmt_positions(ast);
+        let lines = stmt_positions
+            .iter()
+            .chain(&expr_stmt_positions)
+            .filter_map(|pos| pos.line())
+            .map(|line| {
+                let coverage = LineCoverage {
+                    line,
+                    ..Default::default()
+                };
+                (line, coverage)
+            })
+            .collect();
+        Self {
+            stmt_positions,
+            expr_stmt_positions,
+            lines,
+            last_line: None,
+            last_ops: 0,
+        }
+    }
+
+    /// Updates the line counts.
+    pub fn on_debugger_event(
+        &mut self,
+        global: &GlobalRuntimeState,
+        event: &DebuggerEvent,
+        node: ASTNode,
+        source: Option<&str>,
+    ) {
+        // Operations since the last event are attributed to the line of the
+        // node evaluated at that time.
+        let ops = global.num_operations;
+        if let Some(line) = self.last_line {
+            self.line_mut(line).operations += ops - self.last_ops;
+        }
+        self.last_ops = ops;
+
+        // Only steps into nodes count; the other events (e.g. returning from
+        // a function) do not evaluate anything on their own.
+        match event {
+            DebuggerEvent::Start | DebuggerEvent::Step => {}
+            _ => return,
+        }
+        // Only the main script is covered, not code from imported modules.
+        if source.is_some() {
+            self.last_line = None;
+            return;
+        }
+        let pos = node.position();
+        let line = match pos.line() {
+            Some(line) => line,
+            None => return,
+        };
+        self.last_line = Some(line);
+
+        let is_stmt = match node {
+            // A no-op is stepped into when entering a function body.
+            ASTNode::Stmt(Stmt::Noop(..)) => true,
+            ASTNode::Stmt(_) => self.stmt_positions.contains(&pos),
+            ASTNode::Expr(_) => self.expr_stmt_positions.contains(&pos),
+            _ => false,
+        };
+        if is_stmt {
+            self.line_mut(line).hits += 1;
+        }
+    }
+
+    fn line_mut(&mut self, line: usize) -> &mut LineCoverage {
+        self.lines.entry(line).or_insert_with(|| LineCoverage {
+            line,
+            ..Default::default()
+        })
+    }
+
+    pub fn finish(&mut self) -> Coverage {
+        Coverage {
+            lines: std::mem::take(&mut self.lines).into_values().collect(),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+use instant::Instant;
+use rhai::{Engine, EvalAltResult, NativeCallContext};
+use std::rc::Rc;
+
+const MSEC_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+/// A point in time, in milliseconds since the Unix epoch. All components are
+/// in UTC so that scripts behave the same regardless of the time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    msec: i64,
+}
+
+impl DateTime {
+    pub fn from_msec(msec: i64) -> Self {
+        Self { msec }
+    }
+
+    pub fn epoch_msec(self) -> i64 {
+        self.msec
+    }
+
+    fn date(self) -> (i64, u32, u32) {
+        civil_from_days(self.msec.div_euclid(MSEC_PER_DAY))
+    }
+
+    fn time_of_day(self) -> i64 {
+        self.msec.rem_euclid(MSEC_PER_DAY)
+    }
+
+    pub fn year(self) -> i64 {
+        self.date().0
+    }
+
+    pub fn month(self) -> i64 {
+        self.date().1 as i64
+    }
+
+    pub fn day(self) -> i64 {
+        self.date().2 as i64
+    }
+
+    pub fn hour(self) -> i64 {
+        self.time_of_day() / (60 * 60 * 1000)
+    }
+
+    pub fn minute(self) -> i64 {
+        self.time_of_day() / (60 * 1000) % 60
+    }
+
+    pub fn second(self) -> i64 {
+        self.time_of_day() / 1000 % 60
+    }
+
+    pub fn millisecond(self) -> i64 {
+        self.time_of_day() % 1000
+    }
+
+    /// 0 for Sunday to 6 for Saturday, like `Date.prototype.getUTCDay`.
+    pub fn weekday(self) -> i64 {
+        // 1970-01-01 was a Thursday.
+        (self.msec.div_euclid(MSEC_PER_DAY) + 4).rem_euclid(7)
+    }
+
+    /// Formats the time like `Date.prototype.toISOString`, e.g.
+    /// `2021-03-04T05:06:07.089Z`.
+    pub fn to_iso_string(self) -> String {
+        let (year, month, day) = self.date();
+        let year = if (0..=9999).contains(&year) {
+            format!("{:04}", year)
+        } else {
+            format!("{:+07}", year)
+        };
+        format!(
+            "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+            year,
+            month,
+            day,
+            self.hour(),
+            self.minute(),
+            self.second(),
+            self.millisecond()
+        )
+    }
+
+    /// Parses an ISO-8601 date (`YYYY-MM-DD`) or date and time
+    /// (`YYYY-MM-DDTHH:MM[:SS[.sss]]`) with an optional `Z` or `±HH:MM`
+    /// offset. Times without an offset are taken as UTC.
+    pub fn parse_iso(s: &str) -> Option<Self> {
+        let mut p = Parser { s: s.as_bytes() };
+        let year = p.number(4)?;
+        p.expect(b'-')?;
+        let month = p.number(2)?;
+        p.expect(b'-')?;
+        let day = p.number(2)?;
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month as u32) {
+            return None;
+        }
+        let mut msec = days_from_civil(year, month as u32, day as u32) * MSEC_PER_DAY;
+        if p.s.is_empty() {
+            return Some(Self { msec });
+        }
+
+        if p.expect(b'T').is_none() {
+            p.expect(b' ')?;
+        }
+        let hour = p.number(2)?;
+        p.expect(b':')?;
+        let minute = p.number(2)?;
+        let mut second = 0;
+        let mut millisecond = 0;
+        if p.expect(b':').is_some() {
+            second = p.number(2)?;
+            if p.expect(b'.').is_some() {
+                // Digits after the milliseconds are ignored.
+                let digits = p.digits()?;
+                millisecond = digits
+                    .iter()
+                    .chain(b"00")
+                    .take(3)
+                    .fold(0, |n, d| n * 10 + (d - b'0') as i64);
+            }
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        msec += ((hour * 60 + minute) * 60 + second) * 1000 + millisecond;
+
+        match p.s.first() {
+            None => {}
+            Some(b'Z') => p.expect(b'Z')?,
+            Some(&sign @ (b'+' | b'-')) => {
+                p.expect(sign)?;
+                let offset_hour = p.number(2)?;
+                p.expect(b':')?;
+                let offset_minute = p.number(2)?;
+                if offset_hour > 23 || offset_minute > 59 {
+                    return None;
+                }
+                let offset = (offset_hour * 60 + offset_minute) * 60 * 1000;
+                msec += if sign == b'+' { -offset } else { offset };
+            }
+            Some(_) => return None,
+        }
+        if p.s.is_empty() {
+            Some(Self { msec })
+        } else {
+            None
+        }
+    }
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+}
+
+impl<'a> Parser<'a> {
+    fn expect(&mut self, c: u8) -> Option<()> {
+        let (&first, rest) = self.s.split_first()?;
+        if first == c {
+            self.s = rest;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn digits(&mut self) -> Option<&'a [u8]> {
+        let len = self.s.iter().take_while(|c| c.is_ascii_digit()).count();
+        if len == 0 {
+            return None;
+        }
+        let (digits, rest) = self.s.split_at(len);
+        self.s = rest;
+        Some(digits)
+    }
+
+    /// Parses a number of exactly `len` digits.
+    fn number(&mut self, len: usize) -> Option<i64> {
+        if self.s.len() < len || !self.s[..len].iter().all(u8::is_ascii_digit) {
+            return None;
+        }
+        let (digits, rest) = self.s.split_at(len);
+        self.s = rest;
+        Some(digits.iter().fold(0, |n, d| n * 10 + (d - b'0') as i64))
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// The two conversions below are from Howard Hinnant's date algorithms:
+// https://howardhinnant.github.io/date_algorithms.html
+
+/// Returns the number of days since 1970-01-01.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Returns the year, month and day of the number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The current time for a run, which either moves on from the time the run
+/// started or stands still.
+#[derive(Debug)]
+pub struct Clock {
+    start_msec: i64,
+    start_instant: Option<Instant>,
+}
+
+impl Clock {
+    /// A clock starting at the current time.
+    pub fn system() -> Self {
+        Self {
+            start_msec: current_time_msec(),
+            start_instant: Some(Instant::now()),
+        }
+    }
+
+    /// A clock which always returns `msec`.
+    pub fn fixed(msec: i64) -> Self {
+        Self {
+            start_msec: msec,
+            start_instant: None,
+        }
+    }
+
+    /// Milliseconds since the Unix epoch, with a fraction for finer precision.
+    fn now_msec(&self) -> f64 {
+        let elapsed = self
+            .start_instant
+            .map_or(0.0, |instant| instant.elapsed().as_secs_f64() * 1000.0);
+        self.start_msec as f64 + elapsed
+    }
+}
+
+/// Uses `Date.now()` in browsers, where `std::time::SystemTime` panics.
+fn current_time_msec() -> i64 {
+    instant::SystemTime::now()
+        .duration_since(instant::SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as i64)
+}
+
+/// A point in time returned by `timestamp()`, for measuring durations.
+#[derive(Debug, Clone)]
+pub struct Timestamp {
+    msec: f64,
+    clock: Rc<Clock>,
+}
+
+impl Timestamp {
+    /// Seconds since the timestamp, like the built-in `elapsed` property.
+    fn elapsed(&self, context: &NativeCallContext) -> Result<f64, Box<EvalAltResult>> {
+        let now_msec = self.clock.now_msec();
+        if self.msec > now_msec {
+            return Err(arithmetic_error(context, "Time-stamp is later than now"));
+        }
+        Ok((now_msec - self.msec) / 1000.0)
+    }
+
+    fn add_seconds(
+        &self,
+        context: &NativeCallContext,
+        seconds: f64,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        let msec = self.msec + seconds * 1000.0;
+        if !msec.is_finite() {
+            return Err(arithmetic_error(
+                context,
+                format!("Timestamp overflow when adding {} second(s)", seconds),
+            ));
+        }
+        Ok(Self {
+            msec,
+            clock: self.clock.clone(),
+        })
+    }
+
+    fn add_assign(
+        &mut self,
+        context: &NativeCallContext,
+        seconds: f64,
+    ) -> Result<(), Box<EvalAltResult>> {
+        *self = self.add_seconds(context, seconds)?;
+        Ok(())
+    }
+}
+
+fn arithmetic_error(context: &NativeCallContext, message: impl Into<String>) -> Box<EvalAltResult> {
+    EvalAltResult::ErrorArithmetic(message.into(), context.call_position()).into()
+}
+
+/// Registers `now()`, `timestamp()`, `parse_date(str)` and the `DateTime`
+/// type with its component properties and `to_iso_string()`, all reading the
+/// time from `clock`.
+///
+/// `timestamp()` replaces the built-in function of the same name so that it
+/// follows the clock, so the operators and the `elapsed` property and method
+/// of built-in timestamps are registered again for the new type.
+pub fn register_datetime(engine: &mut Engine, clock: Rc<Clock>) {
+    engine.register_type_with_name::<DateTime>("DateTime");
+    engine.register_fn("now", {
+        let clock = clock.clone();
+        move || DateTime::from_msec(clock.now_msec() as i64)
+    });
+    engine.register_fn(
+        "parse_date",
+        |context: NativeCallContext, s: &str| -> Result<DateTime, Box<EvalAltResult>> {
+            DateTime::parse_iso(s).ok_or_else(|| {
+                EvalAltResult::ErrorRuntime(
+                    format!("Invalid ISO-8601 date: {}", s).into(),
+                    context.call_position(),
+                )
+                .into()
+            })
+        },
+    );
+    engine.register_get("year", |dt: &mut DateTime| dt.year());
+    engine.register_get("month", |dt: &mut DateTime| dt.month());
+    engine.register_get("day", |dt: &mut DateTime| dt.day());
+    engine.register_get("hour", |dt: &mut DateTime| dt.hour());
+    engine.register_get("minute", |dt: &mut DateTime| dt.minute());
+    engine.register_get("second", |dt: &mut DateTime| dt.second());
+    engine.register_get("millisecond", |dt: &mut DateTime| dt.millisecond());
+    engine.register_get("weekday", |dt: &mut DateTime| dt.weekday());
+    engine.register_get("epoch_msec", |dt: &mut DateTime| dt.epoch_msec());
+    engine.register_fn("to_iso_string", |dt: &mut DateTime| dt.to_iso_string());
+    engine.register_fn("to_string", |dt: &mut DateTime| dt.to_iso_string());
+    engine.register_fn("to_debug", |dt: &mut DateTime| dt.to_iso_string());
+    engine.register_fn("-", |a: DateTime, b: DateTime| a.msec - b.msec);
+    engine.register_fn("==", |a: DateTime, b: DateTime| a == b);
+    engine.register_fn("!=", |a: DateTime, b: DateTime| a != b);
+    engine.register_fn("<", |a: DateTime, b: DateTime| a < b);
+    engine.register_fn("<=", |a: DateTime, b: DateTime| a <= b);
+    engine.register_fn(">", |a: DateTime, b: DateTime| a > b);
+    engine.register_fn(">=", |a: DateTime, b: DateTime| a >= b);
+
+    engine.register_type_with_name::<Timestamp>("timestamp");
+    engine.register_fn("timestamp", move || Timestamp {
+        msec: clock.now_msec(),
+        clock: clock.clone(),
+    });
+    engine.register_get(
+        "elapsed",
+        |context: NativeCallContext, t: &mut Timestamp| t.elapsed(&context),
+    );
+    engine.register_fn(
+        "elapsed",
+        |context: NativeCallContext, t: &mut Timestamp| t.elapsed(&context),
+    );
+    engine.register_fn("to_string", |_: &mut Timestamp| "<timestamp>");
+    engine.register_fn("to_debug", |_: &mut Timestamp| "<timestamp>");
+    engine.register_fn("-", |a: Timestamp, b: Timestamp| (a.msec - b.msec) / 1000.0);
+    engine.register_fn(
+        "+",
+        |context: NativeCallContext, t: Timestamp, seconds: f64| t.add_seconds(&context, seconds),
+    );
+    engine.register_fn(
+        "+",
+        |context: NativeCallContext, t: Timestamp, seconds: i64| {
+            t.add_seconds(&context, seconds as f64)
+        },
+    );
+    engine.register_fn(
+        "-",
+        |context: NativeCallContext, t: Timestamp, seconds: f64| t.add_seconds(&context, -seconds),
+    );
+    engine.register_fn(
+        "-",
+        |context: NativeCallContext, t: Timestamp, seconds: i64| {
+            t.add_seconds(&context, -(seconds as f64))
+        },
+    );
+    engine.register_fn(
+        "+=",
+        |context: NativeCallContext, t: &mut Timestamp, seconds: f64| {
+            t.add_assign(&context, seconds)
+        },
+    );
+    engine.register_fn(
+        "+=",
+        |context: NativeCallContext, t: &mut Timestamp, seconds: i64| {
+            t.add_assign(&context, seconds as f64)
+        },
+    );
+    engine.register_fn(
+        "-=",
+        |context: NativeCallContext, t: &mut Timestamp, seconds: f64| {
+            t.add_assign(&context, -seconds)
+        },
+    );
+    engine.register_fn(
+        "-=",
+        |context: NativeCallContext, t: &mut Timestamp, seconds: i64| {
+            t.add_assign(&context, -(seconds as f64))
+        },
+    );
+    engine.register_fn("==", |a: Timestamp, b: Timestamp| a.msec == b.msec);
+    engine.register_fn("!=", |a: Timestamp, b: Timestamp| a.msec != b.msec);
+    engine.register_fn("<", |a: Timestamp, b: Timestamp| a.msec < b.msec);
+    engine.register_fn("<=", |a: Timestamp, b: Timestamp| a.msec <= b.msec);
+    engine.register_fn(">", |a: Timestamp, b: Timestamp| a.msec > b.msec);
+    engine.register_fn(">=", |a: Timestamp, b: Timestamp| a.msec >= b.msec);
+}
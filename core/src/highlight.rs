@@ -0,0 +1,463 @@
+//! The state machine of the Rhai editor mode, which highlights and indents
+//! code one line at a time. It works on any `CharStream`, so that it can be
+//! used with different editors and tested natively.
+
+/// A line of text being highlighted, read one character at a time.
+pub trait CharStream {
+    /// The offset of the next character, in the units of the stream.
+    fn pos(&self) -> usize;
+    /// Returns whether nothing has been read from the line yet.
+    fn sol(&self) -> bool;
+    /// Returns whether the whole line has been read.
+    fn eol(&self) -> bool;
+    /// The width of the whitespace at the start of the line, in columns.
+    fn indentation(&self) -> u32;
+    /// Reads the next character, or returns `None` at the end of the line.
+    fn next(&mut self) -> Option<char>;
+    /// Returns the next character without reading it.
+    fn peek(&self) -> Option<char>;
+    /// Moves back before `ch`, which must be the last character read.
+    fn back_up(&mut self, ch: char);
+}
+
+/// A `CharStream` over a line of text in a `&str`.
+pub struct LineStream<'a> {
+    line: &'a str,
+    pos: usize,
+    tab_size: u32,
+}
+
+impl<'a> LineStream<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Self {
+            line,
+            pos: 0,
+            tab_size: 4,
+        }
+    }
+
+    /// Sets the width of tabs for `indentation`, which is 4 by default.
+    pub fn with_tab_size(mut self, tab_size: u32) -> Self {
+        self.tab_size = tab_size;
+        self
+    }
+}
+
+/// Offsets are in bytes.
+impl CharStream for LineStream<'_> {
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn sol(&self) -> bool {
+        self.pos == 0
+    }
+
+    fn eol(&self) -> bool {
+        self.pos >= self.line.len()
+    }
+
+    fn indentation(&self) -> u32 {
+        indentation(self.line.chars(), self.tab_size)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.line[self.pos..].chars().next()
+    }
+
+    fn back_up(&mut self, ch: char) {
+        self.pos -= ch.len_utf8();
+    }
+}
+
+/// Returns the width of the whitespace at the start of a line, in columns.
+fn indentation(line: impl Iterator<Item = char>, tab_size: u32) -> u32 {
+    line.take_while(|c| c.is_whitespace()).fold(0, |column, c| {
+        if c == '\t' {
+            column + tab_size - column % tab_size
+        } else {
+            column + 1
+        }
+    })
+}
+
+/// Lets the Rhai tokenizer read from a `CharStream`. Characters put back by
+/// the tokenizer are always the last ones it read, so the stream is simply
+/// moved back, and they become part of the next token.
+struct InputAdapter<'a, S: ?Sized>(&'a mut S);
+
+impl<S: CharStream + ?Sized> rhai::InputStream for InputAdapter<'_, S> {
+    fn unget(&mut self, ch: char) {
+        self.0.back_up(ch);
+    }
+
+    fn get_next(&mut self) -> Option<char> {
+        self.0.next()
+    }
+
+    fn peek_next(&mut self) -> Option<char> {
+        self.0.peek()
+    }
+}
+
+/// Where the mode is in a binding site, whose identifiers are styled as
+/// definitions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Definition {
+    None,
+    /// After `let`, `const` or `as`: the next identifier is defined.
+    Identifier,
+    /// After `fn`, up to the parameter list.
+    FnName,
+    /// After `for` or `catch`: a single identifier or a parameter list.
+    LoopOrCatchVariables,
+    /// In a parameter list, closed by `)` or, for closures, by `|`.
+    Parameters,
+}
+
+impl Definition {
+    /// Returns the definition state after `token`. `expects_operand` tells
+    /// whether a `|` can start the parameters of a closure rather than be an
+    /// operator.
+    fn next(self, token: &rhai::Token, expects_operand: bool) -> Self {
+        use rhai::Token;
+        match (self, token) {
+            (_, Token::Let | Token::Const | Token::As) => Self::Identifier,
+            (_, Token::Fn) => Self::FnName,
+            (_, Token::For | Token::Catch) => Self::LoopOrCatchVariables,
+            // `fn name(`, or `fn Type.name(` for methods of a type.
+            (Self::FnName, Token::Identifier(_) | Token::StringConstant(_) | Token::Period) => {
+                Self::FnName
+            }
+            (Self::FnName | Self::LoopOrCatchVariables, Token::LeftParen) => Self::Parameters,
+            (Self::Parameters, Token::Identifier(_) | Token::Comma) => Self::Parameters,
+            // The closing `|` of closure parameters ends them.
+            (Self::Parameters, _) => Self::None,
+            (_, Token::Pipe) if expects_operand => Self::Parameters,
+            _ => Self::None,
+        }
+    }
+
+    fn defines_identifier(self) -> bool {
+        self != Self::None
+    }
+}
+
+/// Whether `token` can end an operand, after which `|` is an operator.
+fn ends_operand(token: &rhai::Token) -> bool {
+    matches!(
+        token,
+        rhai::Token::Identifier(_)
+            | rhai::Token::IntegerConstant(_)
+            | rhai::Token::FloatConstant(_)
+            | rhai::Token::CharConstant(_)
+            | rhai::Token::StringConstant(_)
+            | rhai::Token::True
+            | rhai::Token::False
+            | rhai::Token::Unit
+            | rhai::Token::RightParen
+            | rhai::Token::RightBracket
+            | rhai::Token::RightBrace
+    )
+}
+
+/// The state of the mode between tokens and lines.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct State {
+    token_state: rhai::TokenizeState,
+    unclosed_bracket_count: i32,
+    line_indent: u32,
+    definition: Definition,
+    /// Whether the previous token is not the end of an operand.
+    expects_operand: bool,
+    /// Interpolated string brace counting stack
+    interpolated_str_brace_stack: Vec<u8>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            token_state: rhai::TokenizeState {
+                include_comments: true,
+                ..Default::default()
+            },
+            unclosed_bracket_count: 0,
+            line_indent: 0,
+            definition: Definition::None,
+            expects_operand: true,
+            interpolated_str_brace_stack: vec![],
+        }
+    }
+}
+
+/// Reads the next token from `stream` and returns its CodeMirror style, e.g.
+/// `"keyword"`, or `None` if there are no more tokens in the line.
+pub fn token(stream: &mut (impl CharStream + ?Sized), state: &mut State) -> Option<&'static str> {
+    if stream.sol() {
+        state.line_indent = stream.indentation();
+        state.unclosed_bracket_count = 0;
+    }
+
+    let (next_token, _) = rhai::get_next_token(
+        &mut InputAdapter(stream),
+        &mut state.token_state,
+        &mut rhai::Position::default(),
+    );
+    match &next_token {
+        rhai::Token::LeftBrace
+        | rhai::Token::LeftBracket
+        | rhai::Token::LeftParen
+        | rhai::Token::MapStart => {
+            if state.unclosed_bracket_count < 0 {
+                state.unclosed_bracket_count = 0;
+            }
+            state.unclosed_bracket_count += 1;
+        }
+        rhai::Token::RightBrace | rhai::Token::RightBracket | rhai::Token::RightParen => {
+            state.unclosed_bracket_count -= 1;
+        }
+        _ => {}
+    };
+    let res = match &next_token {
+        rhai::Token::IntegerConstant(_) => "number",
+        rhai::Token::FloatConstant(_) => "number",
+        rhai::Token::Identifier(_) => {
+            if state.definition.defines_identifier() {
+                "def"
+            } else {
+                "variable"
+            }
+        }
+        rhai::Token::CharConstant(_) => "string-2",
+        rhai::Token::StringConstant(_) => "string",
+        rhai::Token::InterpolatedString(_) => {
+            state.interpolated_str_brace_stack.push(0);
+            "string"
+        }
+        rhai::Token::LeftBrace => {
+            if let Some(brace_counting) = state.interpolated_str_brace_stack.last_mut() {
+                *brace_counting += 1;
+            }
+            "bracket"
+        }
+        rhai::Token::RightBrace => {
+            if let Some(brace_counting) = state.interpolated_str_brace_stack.last_mut() {
+                *brace_counting -= 1;
+                if *brace_counting == 0 {
+                    state.interpolated_str_brace_stack.pop();
+                    state.token_state.is_within_text_terminated_by = Some("`".into());
+                }
+            }
+            "bracket"
+        }
+        rhai::Token::LeftParen => "bracket",
+        rhai::Token::RightParen => "bracket",
+        rhai::Token::LeftBracket => "bracket",
+        rhai::Token::RightBracket => "bracket",
+        rhai::Token::QuestionBracket => "bracket",
+        rhai::Token::Unit => "keyword",
+        rhai::Token::Plus => "operator",
+        rhai::Token::UnaryPlus => "operator",
+        rhai::Token::Minus => "operator",
+        rhai::Token::UnaryMinus => "operator",
+        rhai::Token::Multiply => "operator",
+        rhai::Token::Divide => "operator",
+        rhai::Token::Modulo => "operator",
+        rhai::Token::PowerOf => "operator",
+        rhai::Token::LeftShift => "operator",
+        rhai::Token::RightShift => "operator",
+        rhai::Token::SemiColon => "operator",
+        rhai::Token::Colon => "operator",
+        rhai::Token::DoubleColon => "operator",
+        rhai::Token::Comma => "operator",
+        rhai::Token::Period => "operator",
+        rhai::Token::ExclusiveRange => "operator",
+        rhai::Token::InclusiveRange => "operator",
+        rhai::Token::MapStart => "bracket",
+        rhai::Token::Equals => "operator",
+        rhai::Token::True => "builtin",
+        rhai::Token::False => "builtin",
+        rhai::Token::Let => "keyword",
+        rhai::Token::Const => "keyword",
+        rhai::Token::If => "keyword",
+        rhai::Token::Else => "keyword",
+        rhai::Token::While => "keyword",
+        rhai::Token::Loop => "keyword",
+        rhai::Token::For => "keyword",
+        rhai::Token::In => "keyword",
+        rhai::Token::NotIn => "keyword",
+        rhai::Token::LessThan => "operator",
+        rhai::Token::GreaterThan => "operator",
+        rhai::Token::LessThanEqualsTo => "operator",
+        rhai::Token::GreaterThanEqualsTo => "operator",
+        rhai::Token::EqualsTo => "operator",
+        rhai::Token::NotEqualsTo => "operator",
+        rhai::Token::Bang => "operator",
+        rhai::Token::Elvis => "operator",
+        rhai::Token::DoubleQuestion => "operator",
+        rhai::Token::Pipe => "operator",
+        rhai::Token::Or => "operator",
+        rhai::Token::XOr => "operator",
+        rhai::Token::Ampersand => "operator",
+        rhai::Token::And => "operator",
+        rhai::Token::Fn => "keyword",
+        rhai::Token::Continue => "keyword",
+        rhai::Token::Break => "keyword",
+        rhai::Token::Return => "keyword",
+        rhai::Token::Throw => "keyword",
+        rhai::Token::PlusAssign => "operator",
+        rhai::Token::MinusAssign => "operator",
+        rhai::Token::MultiplyAssign => "operator",
+        rhai::Token::DivideAssign => "operator",
+        rhai::Token::LeftShiftAssign => "operator",
+        rhai::Token::RightShiftAssign => "operator",
+        rhai::Token::AndAssign => "operator",
+        rhai::Token::OrAssign => "operator",
+        rhai::Token::XOrAssign => "operator",
+        rhai::Token::ModuloAssign => "operator",
+        rhai::Token::PowerOfAssign => "operator",
+        rhai::Token::Private => "keyword",
+        rhai::Token::Import => "keyword",
+        rhai::Token::Export => "keyword",
+        rhai::Token::As => "keyword",
+        rhai::Token::DoubleArrow => "operator",
+        rhai::Token::Underscore => "operator",
+        rhai::Token::Switch => "keyword",
+        rhai::Token::Do => "keyword",
+        rhai::Token::Until => "keyword",
+        rhai::Token::Try => "keyword",
+        rhai::Token::Catch => "keyword",
+        rhai::Token::Comment(_) => "comment",
+        rhai::Token::LexError(_) => "error",
+        rhai::Token::Reserved(_) => "keyword",
+        rhai::Token::Custom(_) => "keyword",
+        rhai::Token::EOF => return None,
+        _ => "error",
+    };
+    if !matches!(next_token, rhai::Token::Comment(_)) {
+        state.definition = state.definition.next(&next_token, state.expects_operand);
+        state.expects_operand = !ends_operand(&next_token);
+    }
+    Some(res)
+}
+
+/// A token of a line returned by `tokenize_line`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineToken {
+    /// The offset where the token ends, in the units of the stream.
+    pub end: usize,
+    /// The style of the token, or `None` for whitespace at the end of the
+    /// line.
+    pub style: Option<&'static str>,
+}
+
+/// Reads all the tokens of the rest of the line, so that editors can
+/// highlight a line in one call instead of one call per token.
+pub fn tokenize_line(stream: &mut (impl CharStream + ?Sized), state: &mut State) -> Vec<LineToken> {
+    let mut tokens = Vec::new();
+    while !stream.eol() {
+        let start = stream.pos();
+        let style = token(stream, state);
+        if stream.pos() == start {
+            // Never happens, according to the tests, but the editor must not
+            // hang if it does.
+            break;
+        }
+        tokens.push(LineToken {
+            end: stream.pos(),
+            style,
+        });
+    }
+    tokens
+}
+
+/// Converts the byte offsets of the tokens of `line` read from a `LineStream`
+/// to UTF-16 offsets, which JavaScript editors use.
+pub fn to_utf16_offsets(line: &str, tokens: &mut [LineToken]) {
+    let mut chars = line.chars();
+    let mut byte_pos = 0;
+    let mut utf16_pos = 0;
+    for token in tokens {
+        while byte_pos < token.end {
+            match chars.next() {
+                Some(c) => {
+                    byte_pos += c.len_utf8();
+                    utf16_pos += c.len_utf16();
+                }
+                None => break,
+            }
+        }
+        token.end = utf16_pos;
+    }
+}
+
+/// A part of the text which can be folded, from just after an opening
+/// bracket to just before its closing bracket on a later line. Offsets are in
+/// bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldRange {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Returns the ranges between matching brackets in `text` which span more
+/// than one line, sorted by `from`. Brackets in strings and comments are
+/// ignored.
+pub fn fold_ranges(text: &str) -> Vec<FoldRange> {
+    let mut state = State::default();
+    // The offsets after the unclosed opening brackets and their lines.
+    let mut open = Vec::new();
+    let mut ranges = Vec::new();
+    let mut line_start = 0;
+    for (line_number, line) in text.split('\n').enumerate() {
+        let mut start = 0;
+        for token in tokenize_line(&mut LineStream::new(line), &mut state) {
+            if token.style == Some("bracket") {
+                let bracket = line[start..token.end].trim_start();
+                if bracket.ends_with(&['{', '[', '('][..]) {
+                    open.push((line_start + token.end, line_number));
+                } else if let Some((from, open_line_number)) = open.pop() {
+                    if open_line_number < line_number {
+                        let to = line_start + token.end - 1;
+                        ranges.push(FoldRange { from, to });
+                    }
+                }
+            }
+            start = token.end;
+        }
+        line_start += line.len() + 1;
+    }
+    ranges.sort_by_key(|range| range.from);
+    ranges
+}
+
+/// Returns the indentation of a new line, given the state at the end of the
+/// previous line and the text after the cursor, or `None` if it cannot be
+/// determined.
+pub fn indent(state: &State, indent_unit: u32, text_after: &str) -> Option<u32> {
+    let should_dedent = || {
+        text_after
+            .trim_start()
+            .starts_with(['}', ']', ')'].as_ref())
+    };
+    #[allow(clippy::collapsible_if)]
+    if state.unclosed_bracket_count > 0 {
+        if should_dedent() {
+            Some(state.line_indent)
+        } else {
+            Some(state.line_indent + indent_unit)
+        }
+    } else {
+        if should_dedent() {
+            Some(state.line_indent.saturating_sub(indent_unit))
+        } else {
+            None
+        }
+    }
+}
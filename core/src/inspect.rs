@@ -0,0 +1,195 @@
+use rhai::debugger::{DebuggerCommand, DebuggerEvent};
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, GlobalRuntimeState, Scope, AST};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Containers nested deeper than this are not expanded.
+const MAX_DEPTH: usize = 16;
+/// Only this many elements of a container are included.
+const MAX_CHILDREN: usize = 1000;
+/// Values longer than this many characters are cut short.
+const MAX_VALUE_LEN: usize = 1000;
+
+/// A value rendered as a tree for display.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValueTree {
+    /// Index or property name of this value in the parent container.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub type_name: String,
+    /// The value on a single line, or a summary of it for containers.
+    pub value: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ValueTree>,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariableInfo {
+    pub name: String,
+    pub is_constant: bool,
+    #[serde(flatten)]
+    pub value: ValueTree,
+}
+
+/// Returns the variables in `scope`, in the order they were defined.
+pub fn scope_variables(engine: &Engine, scope: &Scope) -> Vec<VariableInfo> {
+    let mut variables: Vec<_> = scope
+        .iter_raw()
+        .map(|(name, is_constant, value)| VariableInfo {
+            name: name.to_string(),
+            is_constant,
+            value: value_tree(engine, None, value, 0),
+        })
+        .collect();
+    // The scope is iterated from the most recent variable.
+    variables.reverse();
+    variables
+}
+
+pub fn value_tree(
+    engine: &Engine,
+    key: Option<String>,
+    value: &Dynamic,
+    depth: usize,
+) -> ValueTree {
+    let value = value.flatten_clone();
+    let type_name = engine.map_type_name(value.type_name()).to_string();
+    let expand = depth < MAX_DEPTH;
+
+    let (summary, children) = if let Ok(array) = value.as_array_ref() {
+        let children = if expand {
+            let items = array.iter().enumerate().take(MAX_CHILDREN);
+            items
+                .map(|(i, v)| value_tree(engine, Some(i.to_string()), v, depth + 1))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        (format!("[{} items]", array.len()), children)
+    } else if let Ok(map) = value.as_map_ref() {
+        let children = if expand {
+            let props = map.iter().take(MAX_CHILDREN);
+            props
+                .map(|(k, v)| value_tree(engine, Some(k.to_string()), v, depth + 1))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        (format!("#{{{} properties}}", map.len()), children)
+    } else if let Some(fn_ptr) = value.read_lock::<FnPtr>() {
+        // Closures capture variables by currying them into the pointer.
+        let children = if expand {
+            let curry = fn_ptr.curry().iter().enumerate().take(MAX_CHILDREN);
+            curry
+                .map(|(i, v)| value_tree(engine, Some(format!("curry[{}]", i)), v, depth + 1))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        (format!("Fn({})", fn_ptr.fn_name()), children)
+    } else {
+        let mut s = format!("{:?}", value);
+        if let Some((i, _)) = s.char_indices().nth(MAX_VALUE_LEN) {
+            s.truncate(i);
+            s.push('…');
+        }
+        (s, Vec::new())
+    };
+
+    ValueTree {
+        key,
+        type_name,
+        value: summary,
+        children,
+    }
+}
+
+/// The variables of a call frame at the point a script failed.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrameInfo {
+    /// Name of the script-defined function, or `None` for the top level.
+    pub fn_name: Option<String>,
+    /// Position of the statement or expression being evaluated in the frame.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub variables: Vec<VariableInfo>,
+}
+
+/// Keeps track of where each active call frame was last stopped by the
+/// debugger, identified by the operations count at that point.
+///
+/// Local variables are already removed from the scope by the time an error
+/// reaches a debugger callback, so they are recovered by replaying the script
+/// up to the recorded points with `PostMortemRecorder::replay`.
+#[derive(Default)]
+pub struct PostMortemRecorder {
+    /// Indexed by call stack depth.
+    step_operations: Vec<u64>,
+}
+
+impl PostMortemRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records where the current call frame is stopped.
+    pub fn on_debugger_event(&mut self, global: &GlobalRuntimeState, event: &DebuggerEvent) {
+        if let DebuggerEvent::Start | DebuggerEvent::Step = event {
+            // The operations count is only incremented after the debugger
+            // callback, but at least once between two steps.
+            let depth = global.debugger().call_stack().len();
+            self.step_operations.truncate(depth);
+            self.step_operations.push(global.num_operations);
+        }
+    }
+
+    /// Runs `ast` again on `engine` until the innermost recorded point and
+    /// returns the call frames there, innermost first. As the script is run
+    /// twice, the frames are only accurate if it is deterministic.
+    pub fn replay(&self, mut engine: Engine, ast: &AST) -> Vec<CallFrameInfo> {
+        let last_operations = match self.step_operations.last() {
+            Some(&operations) => operations,
+            None => return Vec::new(),
+        };
+        let frames = Rc::new(RefCell::new(Vec::new()));
+        let step_operations = self.step_operations.clone();
+        engine.set_max_operations(last_operations + 1);
+        #[allow(deprecated)]
+        engine.register_debugger(|_, debugger| debugger, {
+            let frames = frames.clone();
+            move |context, event, node, _source, pos| {
+                let global = context.global_runtime_state();
+                let call_stack = global.debugger().call_stack();
+                let depth = call_stack.len();
+                let is_recorded = matches!(event, DebuggerEvent::Start | DebuggerEvent::Step)
+                    && step_operations.get(depth) == Some(&global.num_operations);
+                if is_recorded {
+                    let pos = if node.position().is_none() {
+                        pos
+                    } else {
+                        node.position()
+                    };
+                    frames.borrow_mut().push(CallFrameInfo {
+                        fn_name: call_stack.last().map(|frame| frame.fn_name.to_string()),
+                        line: pos.line(),
+                        column: pos.position(),
+                        variables: scope_variables(context.engine(), context.scope()),
+                    });
+                    if depth + 1 == step_operations.len() {
+                        return Err(EvalAltResult::ErrorTerminated(Dynamic::UNIT, pos).into());
+                    }
+                }
+                Ok(DebuggerCommand::StepInto)
+            }
+        });
+        let _ = engine.run_ast_with_scope(&mut Scope::new(), ast);
+        drop(engine);
+
+        let mut frames = Rc::try_unwrap(frames).unwrap().into_inner();
+        frames.reverse();
+        frames
+    }
+}
@@ -0,0 +1,71 @@
+use crate::datetime::DateTime;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, NativeCallContext};
+use serde::{Serialize, Serializer};
+
+/// Serializes a value like `Dynamic` does, except for `DateTime`, which is
+/// written as an ISO-8601 string instead of its type name.
+struct JsonValue<'a>(&'a Dynamic);
+
+impl Serialize for JsonValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if let Some(dt) = self.0.read_lock::<DateTime>() {
+            return serializer.serialize_str(&dt.to_iso_string());
+        }
+        if let Some(array) = self.0.read_lock::<Array>() {
+            return serializer.collect_seq(array.iter().map(JsonValue));
+        }
+        if let Some(map) = self.0.read_lock::<Map>() {
+            return serializer.collect_map(map.iter().map(|(k, v)| (k.as_str(), JsonValue(v))));
+        }
+        self.0.serialize(serializer)
+    }
+}
+
+/// Serializes `value` to JSON. Values without a JSON representation are
+/// written as strings: `DateTime` in ISO-8601, function pointers as the name
+/// of the function and other custom types as the name of their Rust type.
+pub fn to_json(value: &Dynamic, pretty: bool) -> Result<String, String> {
+    let json = if pretty {
+        serde_json::to_string_pretty(&JsonValue(value))
+    } else {
+        serde_json::to_string(&JsonValue(value))
+    };
+    json.map_err(|err| format!("Cannot convert {} to JSON: {}", value.type_name(), err))
+}
+
+/// Registers `parse_json(str)`, which accepts any JSON value unlike the one
+/// built into Rhai which only accepts objects, and `to_json(value)` and
+/// `to_json(value, pretty)` for all values.
+pub fn register_json(engine: &mut Engine) {
+    engine.register_fn(
+        "parse_json",
+        |context: NativeCallContext, json: &str| -> Result<Dynamic, Box<EvalAltResult>> {
+            serde_json::from_str(json).map_err(|err| {
+                EvalAltResult::ErrorRuntime(
+                    format!("Invalid JSON: {}", err).into(),
+                    context.call_position(),
+                )
+                .into()
+            })
+        },
+    );
+    engine.register_fn(
+        "to_json",
+        |context: NativeCallContext, value: Dynamic| -> Result<String, Box<EvalAltResult>> {
+            to_json(&value, false).map_err(|err| {
+                EvalAltResult::ErrorRuntime(err.into(), context.call_position()).into()
+            })
+        },
+    );
+    engine.register_fn(
+        "to_json",
+        |context: NativeCallContext,
+         value: Dynamic,
+         pretty: bool|
+         -> Result<String, Box<EvalAltResult>> {
+            to_json(&value, pretty).map_err(|err| {
+                EvalAltResult::ErrorRuntime(err.into(), context.call_position()).into()
+            })
+        },
+    );
+}
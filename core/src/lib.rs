@@ -0,0 +1,26 @@
+//! The part of the Rhai Playground which does not depend on the browser: the
+//! engine configuration, running and analysing scripts, their results and
+//! errors, and highlighting code for the editor. It builds for any target,
+//! and is used by the wasm bindings in the `rhai-playground` crate and by the
+//! command line version of the playground.
+
+#[cfg(feature = "track_alloc")]
+pub mod alloc_tracker;
+pub mod coverage;
+pub mod datetime;
+pub mod highlight;
+pub mod inspect;
+pub mod json;
+pub mod playground;
+pub mod profiler;
+pub mod random;
+pub mod scripting;
+pub mod semantic;
+pub mod trace;
+
+pub use datetime::DateTime;
+pub use playground::{
+    BenchmarkResult, InputProvider, PauseControl, PauseInfo, Playground, RunError, RunOptions,
+    RunResult,
+};
+pub use scripting::dump_ast;
@@ -0,0 +1,248 @@
+//! Runs scripts with the same engine configuration as the playground, but
+//! natively from the command line.
+
+use rhai_playground_core::{
+    dump_ast, DateTime, InputProvider, PauseControl, PauseInfo, Playground, RunOptions,
+};
+use std::cell::Cell;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::process;
+use std::rc::Rc;
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: rhai-playground [OPTIONS] [SCRIPT]
+
+Runs a Rhai script like the playground does. The script is read from standard
+input if SCRIPT is missing or `-`.
+
+Options:
+    --ast               Print the AST of the script instead of running it
+    --profile           Print statistics of the script-defined functions
+    --post-mortem       Print the variables in each call frame on failure
+    --json              Print the value of the script as JSON
+    --seed <N>          Seed for rand() and the other random functions
+    --fixed-time <TIME> Stop the clock at an ISO-8601 time
+    --timeout <MSEC>    Stop the script after this many milliseconds
+    -h, --help          Print this message";
+
+#[derive(Default)]
+struct Args {
+    script_path: Option<String>,
+    dump_ast: bool,
+    options: RunOptions,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = |name: &str| {
+            iter.next()
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+        match arg.as_str() {
+            "--ast" => args.dump_ast = true,
+            "--profile" => args.options.profile = true,
+            "--post-mortem" => args.options.post_mortem = true,
+            "--json" => args.options.json_result = true,
+            "--seed" => {
+                let seed = value("--seed")?;
+                let seed = seed
+                    .parse()
+                    .map_err(|_| format!("Invalid seed: {}", seed))?;
+                args.options.random_seed = Some(seed);
+            }
+            "--fixed-time" => {
+                let time = value("--fixed-time")?;
+                let time = DateTime::parse_iso(&time)
+                    .ok_or_else(|| format!("Invalid ISO-8601 time: {}", time))?;
+                args.options.fixed_time_msec = Some(time.epoch_msec());
+            }
+            "--timeout" => {
+                let timeout = value("--timeout")?;
+                let timeout = timeout
+                    .parse()
+                    .map_err(|_| format!("Invalid timeout: {}", timeout))?;
+                args.options.max_duration_msec = Some(timeout);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                return Err(format!("Unknown option: {}", arg));
+            }
+            _ if args.script_path.is_some() => {
+                return Err("Only one script can be run at a time".into());
+            }
+            _ => args.script_path = Some(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn read_script(path: Option<&str>) -> io::Result<String> {
+    match path {
+        None | Some("-") => {
+            let mut script = String::new();
+            io::stdin().read_to_string(&mut script)?;
+            Ok(script)
+        }
+        Some(path) => std::fs::read_to_string(path),
+    }
+}
+
+/// Reads `input()` lines from standard input. On a terminal, the prompt is
+/// shown on stderr and the terminal echoes the line, so neither is added to
+/// the output.
+struct StdinInput {
+    is_terminal: bool,
+}
+
+impl StdinInput {
+    fn new() -> Self {
+        Self {
+            is_terminal: io::stdin().is_terminal(),
+        }
+    }
+}
+
+impl InputProvider for StdinInput {
+    fn read_line(&self, prompt: &str) -> Option<String> {
+        if self.is_terminal {
+            eprint!("{}", prompt);
+            let _ = io::stderr().flush();
+        }
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()),
+        }
+    }
+
+    fn is_echoed(&self) -> bool {
+        self.is_terminal
+    }
+}
+
+/// Scripts cannot be paused from the command line, but `sleep()` needs a
+/// `PauseControl`.
+struct NoPause;
+
+impl PauseControl for NoPause {
+    fn is_pause_requested(&self) -> bool {
+        false
+    }
+
+    fn wait_for_resume(&self, _info: &PauseInfo) {}
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Reports the number of operations on stderr while a script runs. On a
+/// terminal, the count is kept on a single line which is cleared before the
+/// script prints anything.
+struct Progress {
+    is_terminal: bool,
+    is_shown: Cell<bool>,
+}
+
+impl Progress {
+    fn new() -> Self {
+        Self {
+            is_terminal: io::stderr().is_terminal(),
+            is_shown: Cell::new(false),
+        }
+    }
+
+    fn report(&self, ops: u64) {
+        if self.is_terminal {
+            eprint!("\rOperations: {}", ops);
+            self.is_shown.set(true);
+        } else {
+            eprintln!("Operations: {}", ops);
+        }
+    }
+
+    fn clear(&self) {
+        if self.is_shown.replace(false) {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}\n\n{}", err, USAGE);
+        process::exit(2);
+    });
+    let script = read_script(args.script_path.as_deref()).unwrap_or_else(|err| {
+        eprintln!("Cannot read the script: {}", err);
+        process::exit(2);
+    });
+
+    if args.dump_ast {
+        match dump_ast(&script) {
+            Ok(ast) => println!("{}", ast),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut playground = Playground::new();
+    playground.set_input_provider(Some(Rc::new(StdinInput::new())));
+    playground.set_pause_control(Some(Rc::new(NoPause)));
+    let progress = Rc::new(Progress::new());
+    let result = playground.run_script(
+        &script,
+        &args.options,
+        {
+            let progress = progress.clone();
+            move |s| {
+                progress.clear();
+                println!("{}", s);
+            }
+        },
+        {
+            let progress = progress.clone();
+            move |ops| progress.report(ops)
+        },
+    );
+    progress.clear();
+    match result {
+        Ok(result) => {
+            if let Some(profile) = &result.profile {
+                println!("\nProfile (exclusive msec / inclusive msec / calls / ops):");
+                for f in &profile.functions {
+                    println!(
+                        "  {}/{}: {:.3} / {:.3} / {} / {}",
+                        f.name, f.arity, f.exclusive_msec, f.inclusive_msec, f.calls, f.operations
+                    );
+                }
+            }
+            println!("{}", result.result);
+        }
+        Err(err) => {
+            eprintln!("EXCEPTION: {}", err);
+            for frame in &err.frames {
+                eprintln!(
+                    "  {} (line {}, position {})",
+                    frame.fn_name.as_deref().unwrap_or("<script>"),
+                    frame.line.unwrap_or_default(),
+                    frame.column.unwrap_or_default()
+                );
+                for v in &frame.variables {
+                    let constant = if v.is_constant { "const " } else { "" };
+                    eprintln!("    {}{} = {}", constant, v.name, v.value.value);
+                }
+            }
+            process::exit(1);
+        }
+    }
+}
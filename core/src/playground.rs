@@ -0,0 +1,843 @@
+#[cfg(feature = "track_alloc")]
+use crate::alloc_tracker::{AllocSnapshot, AllocStats};
+use crate::coverage::{Coverage, CoverageRecorder};
+use crate::datetime::{register_datetime, Clock, DateTime};
+use crate::inspect::{scope_variables, CallFrameInfo, PostMortemRecorder, VariableInfo};
+use crate::json::{register_json, to_json};
+use crate::profiler::{Profile, Profiler};
+use crate::random::{register_random, Rng};
+use crate::trace::{Trace, TraceRecorder, DEFAULT_MAX_TRACE_EVENTS};
+use instant::Instant;
+use rhai::debugger::{DebuggerCommand, DebuggerEvent};
+use rhai::{Engine, EvalAltResult, NativeCallContext, Scope};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+
+pub struct Playground {
+    pause_control: Option<Rc<dyn PauseControl>>,
+    input_provider: Option<Rc<dyn InputProvider>>,
+    is_busy_sleep_enabled: bool,
+}
+
+/// Supplies the lines read by `input()` when they are not given up front with
+/// `RunOptions::input_lines`.
+pub trait InputProvider {
+    /// Blocks until a line has been entered. Returns `None` at the end of the
+    /// input.
+    fn read_line(&self, prompt: &str) -> Option<String>;
+    /// Whether the prompt and the entered line are already shown to the
+    /// user, e.g. by a terminal. Otherwise, they are added to the output as
+    /// `[INPUT] <prompt><line>`.
+    fn is_echoed(&self) -> bool {
+        false
+    }
+}
+
+/// Lets a running script be paused from outside, e.g. by the page while the
+/// script runs in a worker.
+pub trait PauseControl {
+    /// Whether a pause has been requested. Polled whenever progress is
+    /// sampled.
+    fn is_pause_requested(&self) -> bool;
+    /// Blocks until the script is resumed.
+    fn wait_for_resume(&self, info: &PauseInfo);
+    /// Blocks for `duration`, or less if a pause is requested in the
+    /// meantime. Used by `sleep()`.
+    fn sleep(&self, duration: Duration);
+}
+
+/// Where a script has been paused.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseInfo {
+    pub operations: u64,
+    /// The position is only known for instrumented runs, which pause at the
+    /// next statement or expression instead of right away.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+/// Options for a single `Playground::run_script` call.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RunOptions {
+    /// Maximum total length (in bytes) of the output. Lines printed after the
+    /// limit has been reached are dropped. `None` means unlimited.
+    pub max_output_len: Option<usize>,
+    /// Collect per-function statistics of script-defined functions.
+    pub profile: bool,
+    /// Also output the profile in the collapsed stacks format.
+    pub collapsed_stacks: bool,
+    /// Collect per-line execution counts.
+    pub coverage: bool,
+    /// Record an event for each executed statement and each variable
+    /// definition or assignment.
+    pub trace: bool,
+    /// Maximum number of trace events to record. Defaults to
+    /// `DEFAULT_MAX_TRACE_EVENTS`.
+    pub max_trace_events: Option<usize>,
+    /// Return the top-level variables left after the script finished.
+    pub inspect_scope: bool,
+    /// When the script fails, return the variables in each call frame.
+    pub post_mortem: bool,
+    /// Terminate the script once it has been running for this many
+    /// milliseconds. The time is only checked when progress is sampled, so
+    /// the script may run for a little longer.
+    pub max_duration_msec: Option<u64>,
+    /// The lines returned by `input()`, for running scripts without
+    /// interaction. If `None`, the `InputProvider` of the playground is used.
+    pub input_lines: Option<Vec<String>>,
+    /// Seed for `rand()` and the other random functions, for reproducible
+    /// runs. If `None`, a different seed is used for every run.
+    pub random_seed: Option<u64>,
+    /// Return the value of the script as pretty-printed JSON instead of
+    /// converting it with `to_string`.
+    pub json_result: bool,
+    /// Milliseconds since the Unix epoch to stop the clock at for `now()`
+    /// and `timestamp()`, for reproducible runs.
+    pub fixed_time_msec: Option<i64>,
+}
+
+impl RunOptions {
+    /// Whether the run needs a debugger registered on the engine.
+    fn is_instrumented(&self) -> bool {
+        self.profile || self.coverage || self.trace || self.post_mortem
+    }
+}
+
+/// The result of a successful `Playground::run_script` call.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunResult {
+    /// The value returned by the script, converted with `to_string` or to
+    /// JSON with `RunOptions::json_result`.
+    pub result: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage: Option<Coverage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Trace>,
+    /// The top-level variables, in the order they were defined.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Vec<VariableInfo>>,
+    /// Only available with the `track_alloc` feature.
+    #[cfg(feature = "track_alloc")]
+    pub alloc: AllocStats,
+}
+
+/// The error of a failed `Playground::run_script` call.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunError {
+    pub message: String,
+    /// With `RunOptions::post_mortem`, the call frames at the point of
+    /// failure, innermost first and the top level last.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub frames: Vec<CallFrameInfo>,
+    /// Whether the script was terminated by `RunOptions::max_duration_msec`.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_timeout: bool,
+}
+
+impl From<String> for RunError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            frames: Vec::new(),
+            is_timeout: false,
+        }
+    }
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Pauses a running script using the `PauseControl` of the playground.
+struct Pauser {
+    control: Rc<dyn PauseControl>,
+    output: Rc<RefCell<OutputBuffer>>,
+    progress_callback: Rc<dyn Fn(u64)>,
+    deadline: Rc<Deadline>,
+    /// Set when the pause is to happen at the next debugger step.
+    is_pending: Cell<bool>,
+    /// The operations count at the latest call to `on_progress`.
+    operations: Cell<u64>,
+}
+
+impl Pauser {
+    fn pause(&self, info: &PauseInfo) {
+        self.is_pending.set(false);
+        self.output.borrow_mut().flush();
+        (self.progress_callback)(info.operations);
+        let start_instant = Instant::now();
+        self.control.wait_for_resume(info);
+        self.deadline.add_paused_duration(start_instant.elapsed());
+    }
+}
+
+/// Keeps track of `RunOptions::max_duration_msec`. Time spent paused does not
+/// count towards it.
+struct Deadline {
+    start_instant: Instant,
+    max_duration: Option<Duration>,
+    paused_duration: Cell<Duration>,
+    is_timeout: Cell<bool>,
+}
+
+impl Deadline {
+    fn new(max_duration_msec: Option<u64>) -> Self {
+        Self {
+            start_instant: Instant::now(),
+            max_duration: max_duration_msec.map(Duration::from_millis),
+            paused_duration: Cell::new(Duration::ZERO),
+            is_timeout: Cell::new(false),
+        }
+    }
+
+    /// Returns the time left, or `None` without a time limit.
+    fn remaining(&self) -> Option<Duration> {
+        let max_duration = self.max_duration?;
+        let elapsed = self
+            .start_instant
+            .elapsed()
+            .saturating_sub(self.paused_duration.get());
+        Some(max_duration.saturating_sub(elapsed))
+    }
+
+    /// Returns whether the time is up, remembering it for the error message.
+    fn check_timeout(&self) -> bool {
+        if self.remaining() == Some(Duration::ZERO) {
+            self.is_timeout.set(true);
+        }
+        self.is_timeout.get()
+    }
+
+    fn add_paused_duration(&self, duration: Duration) {
+        self.paused_duration
+            .set(self.paused_duration.get() + duration);
+    }
+}
+
+/// Implements the `sleep()` function for a single run.
+struct Sleep {
+    pauser: Option<Rc<Pauser>>,
+    /// Whether to spin without a pauser instead of failing.
+    is_busy: bool,
+    output: Rc<RefCell<OutputBuffer>>,
+    deadline: Rc<Deadline>,
+}
+
+impl Sleep {
+    fn sleep(&self, context: &NativeCallContext, msec: i64) -> Result<(), Box<EvalAltResult>> {
+        let pos = context.call_position();
+        if self.pauser.is_none() && !self.is_busy {
+            return Err(
+                EvalAltResult::ErrorRuntime("sleep() is not supported here".into(), pos).into(),
+            );
+        }
+        // Show everything printed so far before blocking.
+        self.output.borrow_mut().flush();
+        let mut remaining = Duration::from_millis(msec.max(0) as u64);
+        loop {
+            if self.deadline.check_timeout() {
+                return Err(EvalAltResult::ErrorTerminated(rhai::Dynamic::UNIT, pos).into());
+            }
+            if remaining.is_zero() {
+                return Ok(());
+            }
+            let pauser = match &self.pauser {
+                Some(pauser) => pauser,
+                None => {
+                    // Without a way to block, e.g. in a worker of a page that
+                    // is not cross-origin isolated, spin until the time is up.
+                    let start_instant = Instant::now();
+                    while start_instant.elapsed() < remaining {
+                        if self.deadline.check_timeout() {
+                            break;
+                        }
+                    }
+                    remaining = remaining.saturating_sub(start_instant.elapsed());
+                    continue;
+                }
+            };
+            let duration = self
+                .deadline
+                .remaining()
+                .map_or(remaining, |left| left.min(remaining));
+            let start_instant = Instant::now();
+            pauser.control.sleep(duration);
+            remaining = remaining.saturating_sub(start_instant.elapsed());
+            if pauser.control.is_pause_requested() {
+                pauser.pause(&PauseInfo {
+                    operations: pauser.operations.get(),
+                    line: pos.line(),
+                    column: pos.position(),
+                });
+            }
+        }
+    }
+}
+
+/// Implements the `input()` function for a single run.
+struct Input {
+    lines: RefCell<VecDeque<String>>,
+    provider: Option<Rc<dyn InputProvider>>,
+    output: Rc<RefCell<OutputBuffer>>,
+    /// Every line returned so far, so that the run can be replayed.
+    history: RefCell<Vec<String>>,
+}
+
+impl Input {
+    fn read_line(
+        &self,
+        context: &NativeCallContext,
+        prompt: &str,
+    ) -> Result<String, Box<EvalAltResult>> {
+        let mut is_echoed = false;
+        let line = match &self.provider {
+            Some(provider) => {
+                // Show everything printed so far before blocking.
+                self.output.borrow_mut().flush();
+                is_echoed = provider.is_echoed();
+                provider.read_line(prompt)
+            }
+            None => self.lines.borrow_mut().pop_front(),
+        };
+        let line = line.ok_or_else(|| {
+            EvalAltResult::ErrorRuntime(
+                "No more input is available".into(),
+                context.call_position(),
+            )
+        })?;
+        if !is_echoed {
+            self.output
+                .borrow_mut()
+                .push_line(&format!("[INPUT] {}{}", prompt, line));
+        }
+        self.history.borrow_mut().push(line.clone());
+        Ok(line)
+    }
+}
+
+impl Input {
+    /// Input which fails every read, for runs without input.
+    fn none() -> Self {
+        Self {
+            lines: RefCell::new(VecDeque::new()),
+            provider: None,
+            output: Rc::new(RefCell::new(OutputBuffer::new(None, |_| {}))),
+            history: RefCell::new(Vec::new()),
+        }
+    }
+}
+
+fn register_input(engine: &mut Engine, input: Rc<Input>) {
+    engine.register_fn("input", {
+        let input = input.clone();
+        move |context: NativeCallContext| input.read_line(&context, "")
+    });
+    engine.register_fn("input", move |context: NativeCallContext, prompt: &str| {
+        input.read_line(&context, prompt)
+    });
+}
+
+fn register_sleep(engine: &mut Engine, sleep: Rc<Sleep>) {
+    engine.register_fn("sleep", move |context: NativeCallContext, msec: i64| {
+        sleep.sleep(&context, msec)
+    });
+}
+
+/// Registers the functions whose state belongs to a single run, apart from
+/// `sleep()`: `input()`, the random functions and the date and time
+/// functions.
+fn register_run_functions(
+    engine: &mut Engine,
+    input: Rc<Input>,
+    random_seed: u64,
+    clock: Rc<Clock>,
+) {
+    register_input(engine, input);
+    register_random(engine, Rc::new(RefCell::new(Rng::new(random_seed))));
+    register_datetime(engine, clock);
+}
+
+/// Buffers the lines from `print` and `debug` so that they are passed on in
+/// batches instead of crossing over to JavaScript once per line.
+struct OutputBuffer {
+    buf: String,
+    total_len: usize,
+    max_len: Option<usize>,
+    is_truncated: bool,
+    flush_callback: Box<dyn Fn(&str)>,
+}
+
+impl OutputBuffer {
+    /// Flush the buffer once it grows beyond this many bytes, even if the
+    /// time budget checked by `on_progress` has not been reached yet.
+    const FLUSH_LEN: usize = 64 * 1024;
+
+    fn new(max_len: Option<usize>, flush_callback: impl Fn(&str) + 'static) -> Self {
+        Self {
+            buf: String::new(),
+            total_len: 0,
+            max_len,
+            is_truncated: false,
+            flush_callback: Box::new(flush_callback),
+        }
+    }
+
+    fn push_line(&mut self, line: &str) {
+        if self.is_truncated {
+            return;
+        }
+        if let Some(max_len) = self.max_len {
+            if self.total_len + line.len() > max_len {
+                self.is_truncated = true;
+                let notice = format!(
+                    "[Output truncated: exceeded the limit of {} bytes]",
+                    max_len
+                );
+                self.append(&notice);
+                self.flush();
+                return;
+            }
+        }
+        self.total_len += line.len() + 1;
+        self.append(line);
+        if self.buf.len() >= Self::FLUSH_LEN {
+            self.flush();
+        }
+    }
+
+    fn append(&mut self, line: &str) {
+        if !self.buf.is_empty() {
+            self.buf.push('\n');
+        }
+        self.buf.push_str(line);
+    }
+
+    fn flush(&mut self) {
+        if !self.buf.is_empty() {
+            (self.flush_callback)(&self.buf);
+            self.buf.clear();
+        }
+    }
+}
+
+/// Converts the value of a script for `RunResult::result`. `Dynamic` only
+/// knows the type names of custom types, so those are formatted here.
+fn result_to_string(result: &rhai::Dynamic) -> String {
+    match result.read_lock::<DateTime>() {
+        Some(dt) => dt.to_iso_string(),
+        None => result.to_string(),
+    }
+}
+
+fn create_engine() -> Engine {
+    let mut engine = rhai::Engine::new();
+    engine.disable_symbol("eval");
+    register_json(&mut engine);
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine
+}
+
+impl Default for Playground {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Playground {
+    pub fn new() -> Self {
+        Self {
+            pause_control: None,
+            input_provider: None,
+            is_busy_sleep_enabled: false,
+        }
+    }
+
+    /// Sets where `input()` reads lines from when no `RunOptions::input_lines`
+    /// are given. Without an `InputProvider`, `input()` fails.
+    pub fn set_input_provider(&mut self, input_provider: Option<Rc<dyn InputProvider>>) {
+        self.input_provider = input_provider;
+    }
+
+    /// Sets how running scripts are paused. Without a `PauseControl`, scripts
+    /// cannot be paused.
+    pub fn set_pause_control(&mut self, pause_control: Option<Rc<dyn PauseControl>>) {
+        self.pause_control = pause_control;
+    }
+
+    /// Lets `sleep()` spin until the time is up when there is no
+    /// `PauseControl` to block with. Off by default, as spinning on the main
+    /// thread of a page freezes it, in which case `sleep()` fails instead.
+    pub fn set_busy_sleep(&mut self, enabled: bool) {
+        self.is_busy_sleep_enabled = enabled;
+    }
+
+    /// Runs a script. Output from `print` and `debug` is passed to
+    /// `output_callback` in batches of one or more lines separated by `\n`.
+    pub fn run_script(
+        &mut self,
+        script: &str,
+        options: &RunOptions,
+        output_callback: impl Fn(&str) + 'static,
+        progress_callback: impl Fn(u64) + 'static,
+    ) -> Result<RunResult, RunError> {
+        #[cfg(feature = "track_alloc")]
+        let alloc_snapshot = AllocSnapshot::start();
+
+        // Every run gets its own engine, so that the functions registered for
+        // the run, and the JavaScript functions they hold, are released when
+        // it finishes.
+        let mut engine = create_engine();
+        let engine = &mut engine;
+
+        let output = Rc::new(RefCell::new(OutputBuffer::new(
+            options.max_output_len,
+            output_callback,
+        )));
+
+        engine.on_print({
+            let output = output.clone();
+            move |s| output.borrow_mut().push_line(&format!("[PRINT] {}", s))
+        });
+        engine.on_debug({
+            let output = output.clone();
+            move |s, src, pos| {
+                output.borrow_mut().push_line(&src.map_or_else(
+                    || format!("[DEBUG] <script>:[{}] {}", pos, s),
+                    |src| format!("[DEBUG] {}:[{}] {}", src, pos, s),
+                ))
+            }
+        });
+        let input = Rc::new(match &options.input_lines {
+            Some(lines) => Input {
+                lines: RefCell::new(lines.iter().cloned().collect()),
+                provider: None,
+                output: output.clone(),
+                history: RefCell::new(Vec::new()),
+            },
+            None => Input {
+                lines: RefCell::new(VecDeque::new()),
+                provider: self.input_provider.clone(),
+                output: output.clone(),
+                history: RefCell::new(Vec::new()),
+            },
+        });
+        let random_seed = options.random_seed.unwrap_or_else(Rng::entropy_seed);
+        let clock = Rc::new(match options.fixed_time_msec {
+            Some(msec) => Clock::fixed(msec),
+            None => Clock::system(),
+        });
+        register_run_functions(engine, input.clone(), random_seed, clock.clone());
+        let script_ast = engine.compile(script).map_err(|e| e.to_string())?;
+
+        let progress_callback: Rc<dyn Fn(u64)> = Rc::new(progress_callback);
+        let deadline = Rc::new(Deadline::new(options.max_duration_msec));
+        let pauser = self.pause_control.clone().map(|control| {
+            Rc::new(Pauser {
+                control,
+                output: output.clone(),
+                progress_callback: progress_callback.clone(),
+                deadline: deadline.clone(),
+                is_pending: Cell::new(false),
+                operations: Cell::new(0),
+            })
+        });
+        register_sleep(
+            engine,
+            Rc::new(Sleep {
+                pauser: pauser.clone(),
+                is_busy: self.is_busy_sleep_enabled,
+                output: output.clone(),
+                deadline: deadline.clone(),
+            }),
+        );
+
+        let profiler = if options.profile {
+            Some(Rc::new(RefCell::new(Profiler::new(&script_ast))))
+        } else {
+            None
+        };
+        let coverage = if options.coverage {
+            Some(Rc::new(RefCell::new(CoverageRecorder::new(&script_ast))))
+        } else {
+            None
+        };
+        let trace = if options.trace {
+            let max_events = options.max_trace_events.unwrap_or(DEFAULT_MAX_TRACE_EVENTS);
+            Some(Rc::new(RefCell::new(TraceRecorder::new(
+                &script_ast,
+                max_events,
+            ))))
+        } else {
+            None
+        };
+        let post_mortem = if options.post_mortem {
+            Some(Rc::new(RefCell::new(PostMortemRecorder::new())))
+        } else {
+            None
+        };
+        if options.is_instrumented() {
+            let profiler = profiler.clone();
+            let coverage = coverage.clone();
+            let trace = trace.clone();
+            let post_mortem = post_mortem.clone();
+            let pauser = pauser.clone();
+            // The `on_debugger_event` methods of the recorders expect to see
+            // every step, so the debugger always steps into everything.
+            #[allow(deprecated)]
+            engine.register_debugger(
+                |_, debugger| debugger,
+                move |context, event, node, source, pos| {
+                    let global = context.global_runtime_state();
+                    if let Some(pauser) = &pauser {
+                        let is_step = matches!(event, DebuggerEvent::Start | DebuggerEvent::Step);
+                        if is_step && pauser.is_pending.get() {
+                            let pos = if node.position().is_none() {
+                                pos
+                            } else {
+                                node.position()
+                            };
+                            pauser.pause(&PauseInfo {
+                                operations: global.num_operations,
+                                line: pos.line(),
+                                column: pos.position(),
+                            });
+                        }
+                    }
+                    if let Some(profiler) = &profiler {
+                        profiler.borrow_mut().on_debugger_event(global, &event);
+                    }
+                    if let Some(coverage) = &coverage {
+                        coverage
+                            .borrow_mut()
+                            .on_debugger_event(global, &event, node, source);
+                    }
+                    if let Some(trace) = &trace {
+                        trace.borrow_mut().on_debugger_event(&context, &event, node);
+                    }
+                    if let Some(post_mortem) = &post_mortem {
+                        post_mortem.borrow_mut().on_debugger_event(global, &event);
+                    }
+                    Ok(DebuggerCommand::StepInto)
+                },
+            );
+        }
+
+        let interval = RefCell::new(1000);
+        let last_instant = RefCell::new(Instant::now());
+        engine.on_progress({
+            let output = output.clone();
+            let deadline = deadline.clone();
+            let is_instrumented = options.is_instrumented();
+            move |ops| {
+                if let Some(pauser) = &pauser {
+                    pauser.operations.set(ops);
+                }
+                let interval_value = *interval.borrow();
+                if ops % interval_value == 0 {
+                    if let Some(pauser) = &pauser {
+                        if pauser.control.is_pause_requested() {
+                            if is_instrumented {
+                                pauser.is_pending.set(true);
+                            } else {
+                                pauser.pause(&PauseInfo {
+                                    operations: ops,
+                                    line: None,
+                                    column: None,
+                                });
+                            }
+                        }
+                    }
+                    let mut last_instant = last_instant.borrow_mut();
+                    let new_instant = Instant::now();
+                    if deadline.check_timeout() {
+                        return Some(rhai::Dynamic::UNIT);
+                    }
+                    let duration_msec = new_instant.duration_since(*last_instant).as_millis();
+                    if duration_msec < 50 {
+                        interval.replace(interval_value * 10);
+                    } else if duration_msec >= 100 {
+                        output.borrow_mut().flush();
+                        progress_callback(ops);
+                        *last_instant = new_instant;
+                        if duration_msec >= 500 && interval_value > 1 {
+                            interval.replace(interval_value / 10);
+                        }
+                    }
+                }
+                None
+            }
+        });
+
+        let mut scope = Scope::new();
+        let result = engine.eval_ast_with_scope::<rhai::Dynamic>(&mut scope, &script_ast);
+        output.borrow_mut().flush();
+        if deadline.is_timeout.get() {
+            return Err(RunError {
+                message: format!(
+                    "Script timed out: exceeded the time limit of {} ms",
+                    options.max_duration_msec.unwrap_or_default()
+                ),
+                frames: Vec::new(),
+                is_timeout: true,
+            });
+        }
+        let result = result.map_err(|err| RunError {
+            message: err.to_string(),
+            // Replaying is skipped for errors like running out of operations
+            // as it could take just as long as the failed run.
+            frames: match &post_mortem {
+                Some(post_mortem) if !err.is_system_exception() => {
+                    // The replay gets the same input without asking again.
+                    let mut replay_engine = create_engine();
+                    let replay_input = Input {
+                        lines: RefCell::new(input.history.take().into()),
+                        ..Input::none()
+                    };
+                    register_run_functions(
+                        &mut replay_engine,
+                        Rc::new(replay_input),
+                        random_seed,
+                        clock.clone(),
+                    );
+                    replay_engine.register_fn("sleep", |_: i64| {});
+                    post_mortem.borrow().replay(replay_engine, &script_ast)
+                }
+                _ => Vec::new(),
+            },
+            is_timeout: false,
+        })?;
+
+        Ok(RunResult {
+            result: if options.json_result {
+                to_json(&result, true)?
+            } else {
+                result_to_string(&result)
+            },
+            profile: profiler.map(|p| p.borrow_mut().finish(options.collapsed_stacks)),
+            coverage: coverage.map(|c| c.borrow_mut().finish()),
+            trace: trace.map(|t| t.borrow_mut().finish()),
+            scope: if options.inspect_scope {
+                Some(scope_variables(engine, &scope))
+            } else {
+                None
+            },
+            #[cfg(feature = "track_alloc")]
+            alloc: alloc_snapshot.finish(),
+        })
+    }
+
+    /// Compiles a script once and evaluates it `iterations` times with
+    /// `print` and `debug` output suppressed, returning timing statistics.
+    ///
+    /// The script has the same functions as in `run_script`, with the state
+    /// of a run shared by the iterations: `input()` fails, the random
+    /// functions use a new seed and `sleep()` cannot be paused, so it only
+    /// works with `set_busy_sleep`.
+    pub fn benchmark(&mut self, script: &str, iterations: u32) -> Result<BenchmarkResult, String> {
+        if iterations == 0 {
+            return Err("The number of iterations must be at least 1".into());
+        }
+
+        let mut engine = create_engine();
+        let engine = &mut engine;
+        let input = Rc::new(Input::none());
+        let output = input.output.clone();
+        register_run_functions(engine, input, Rng::entropy_seed(), Rc::new(Clock::system()));
+        register_sleep(
+            engine,
+            Rc::new(Sleep {
+                pauser: None,
+                is_busy: self.is_busy_sleep_enabled,
+                output,
+                deadline: Rc::new(Deadline::new(None)),
+            }),
+        );
+        let script_ast = engine.compile(script).map_err(|e| e.to_string())?;
+
+        let last_ops = Rc::new(Cell::new(0));
+        engine.on_progress({
+            let last_ops = last_ops.clone();
+            move |ops| {
+                last_ops.set(ops);
+                None
+            }
+        });
+
+        let mut durations_msec = Vec::with_capacity(iterations as usize);
+        let mut total_ops = 0;
+        for _ in 0..iterations {
+            last_ops.set(0);
+            let start = Instant::now();
+            engine.run_ast(&script_ast).map_err(|e| e.to_string())?;
+            durations_msec.push(start.elapsed().as_secs_f64() * 1000.0);
+            total_ops += last_ops.get();
+        }
+
+        Ok(BenchmarkResult::from_samples(durations_msec, total_ops))
+    }
+}
+
+/// Timing statistics returned by `Playground::benchmark`. All durations are
+/// in milliseconds of wall time per iteration.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkResult {
+    pub iterations: u32,
+    pub min_msec: f64,
+    pub max_msec: f64,
+    pub mean_msec: f64,
+    pub median_msec: f64,
+    pub stddev_msec: f64,
+    /// Operations counted by the engine in each iteration, on average.
+    pub operations: u64,
+    pub operations_per_sec: f64,
+}
+
+impl BenchmarkResult {
+    fn from_samples(mut durations_msec: Vec<f64>, total_ops: u64) -> Self {
+        durations_msec.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = durations_msec.len();
+        let total_msec: f64 = durations_msec.iter().sum();
+        let mean_msec = total_msec / n as f64;
+        let median_msec = if n.is_multiple_of(2) {
+            (durations_msec[n / 2 - 1] + durations_msec[n / 2]) / 2.0
+        } else {
+            durations_msec[n / 2]
+        };
+        let stddev_msec = if n > 1 {
+            let sum_sq: f64 = durations_msec
+                .iter()
+                .map(|d| (d - mean_msec) * (d - mean_msec))
+                .sum();
+            (sum_sq / (n - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let operations_per_sec = if total_msec > 0.0 {
+            total_ops as f64 / (total_msec / 1000.0)
+        } else {
+            0.0
+        };
+        Self {
+            iterations: n as u32,
+            min_msec: durations_msec[0],
+            max_msec: durations_msec[n - 1],
+            mean_msec,
+            median_msec,
+            stddev_msec,
+            operations: total_ops / n as u64,
+            operations_per_sec,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use instant::Instant;
+use rhai::debugger::DebuggerEvent;
+use rhai::{GlobalRuntimeState, AST};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+/// Name of the root frame in collapsed stacks, which accounts for the time
+/// spent in top-level statements.
+const ROOT_FRAME_NAME: &str = "<script>";
+
+/// Per-function statistics collected during a profiling run.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FunctionProfile {
+    pub name: String,
+    pub arity: usize,
+    pub calls: u64,
+    /// Operations counted while the function was on the call stack.
+    pub operations: u64,
+    /// Wall time spent in the function, including the functions it called.
+    pub inclusive_msec: f64,
+    /// Wall time spent in the function itself, excluding the script-defined
+    /// functions it called.
+    pub exclusive_msec: f64,
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    /// Script-defined functions sorted by exclusive time, descending.
+    pub functions: Vec<FunctionProfile>,
+    /// The profile in the "collapsed stacks" format used by flame graph
+    /// tools: one `a;b;c <microseconds>` line per distinct call stack.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collapsed_stacks: Option<String>,
+}
+
+struct Frame {
+    /// Index into `Profiler::stats` if this is a script-defined function,
+    /// `None` for native functions, which are not reported.
+    stats_index: Option<usize>,
+    start: Instant,
+    start_ops: u64,
+    /// Inclusive time of the script-defined functions called from this one.
+    children_msec: f64,
+}
+
+#[derive(Default)]
+struct Stats {
+    calls: u64,
+    operations: u64,
+    inclusive_msec: f64,
+    exclusive_msec: f64,
+}
+
+pub struct Profiler {
+    script_fns: HashSet<(String, usize)>,
+    keys: Vec<(String, usize)>,
+    stats: Vec<Stats>,
+    /// Mirrors the call stack of the engine's debugger.
+    stack: Vec<Frame>,
+    start: Instant,
+    last_ops: u64,
+    /// Inclusive time of the top-level script-defined function calls.
+    top_level_children_msec: f64,
+    /// Exclusive time keyed by the names of the script-defined functions on
+    /// the stack, for the collapsed stacks output.
+    collapsed: BTreeMap<String, f64>,
+}
+
+impl Profiler {
+    pub fn new(ast: &AST) -> Self {
+        Self {
+            script_fns: ast
+                .iter_functions()
+                .map(|f| (f.name.to_string(), f.params.len()))
+                .collect(),
+            keys: Vec::new(),
+            stats: Vec::new(),
+            stack: Vec::new(),
+            start: Instant::now(),
+            last_ops: 0,
+            top_level_children_msec: 0.0,
+            collapsed: BTreeMap::new(),
+        }
+    }
+
+    /// Updates the profile.
+    pub fn on_debugger_event(&mut self, global: &GlobalRuntimeState, event: &DebuggerEvent) {
+        let call_stack = global.debugger().call_stack();
+        let ops = global.num_operations;
+        if let DebuggerEvent::Start = event {
+            self.start = Instant::now();
+        }
+        self.last_ops = ops;
+        for frame in call_stack.iter().skip(self.stack.len()) {
+            self.push_frame(&frame.fn_name, frame.args.len(), ops);
+        }
+        match event {
+            DebuggerEvent::FunctionExitWithValue(_) | DebuggerEvent::FunctionExitWithError(_) => {
+                // The exiting frame is still on the call stack.
+                let depth = call_stack.len().saturating_sub(1);
+                while self.stack.len() > depth {
+                    self.pop_frame(ops);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push_frame(&mut self, name: &str, arity: usize, ops: u64) {
+        let key = (name.to_string(), arity);
+        let stats_index = if self.script_fns.contains(&key) {
+            Some(match self.keys.iter().position(|k| *k == key) {
+                Some(i) => i,
+                None => {
+                    self.keys.push(key);
+                    self.stats.push(Stats::default());
+                    self.keys.len() - 1
+                }
+            })
+        } else {
+            None
+        };
+        self.stack.push(Frame {
+            stats_index,
+            start: Instant::now(),
+            start_ops: ops,
+            children_msec: 0.0,
+        });
+    }
+
+    fn pop_frame(&mut self, ops: u64) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let stats_index = match frame.stats_index {
+            Some(i) => i,
+            None => return,
+        };
+        let inclusive_msec = frame.start.elapsed().as_secs_f64() * 1000.0;
+        let exclusive_msec = (inclusive_msec - frame.children_msec).max(0.0);
+        // For recursive calls, only the outermost call counts towards the
+        // inclusive figures so that time is not counted more than once.
+        let is_recursive = self
+            .stack
+            .iter()
+            .any(|f| f.stats_index == Some(stats_index));
+
+        let path = self.collapsed_path(&self.keys[stats_index].0);
+        *self.collapsed.entry(path).or_default() += exclusive_msec;
+
+        let stats = &mut self.stats[stats_index];
+        stats.calls += 1;
+        stats.exclusive_msec += exclusive_msec;
+        if !is_recursive {
+            stats.inclusive_msec += inclusive_msec;
+            stats.operations += ops - frame.start_ops;
+        }
+
+        match self
+            .stack
+            .iter_mut()
+            .rev()
+            .find(|f| f.stats_index.is_some())
+        {
+            Some(parent) => parent.children_msec += inclusive_msec,
+            None => self.top_level_children_msec += inclusive_msec,
+        }
+    }
+
+    fn collapsed_path(&self, leaf: &str) -> String {
+        let mut path = ROOT_FRAME_NAME.to_string();
+        for i in self.stack.iter().filter_map(|f| f.stats_index) {
+            path.push(';');
+            path.push_str(&self.keys[i].0);
+        }
+        path.push(';');
+        path.push_str(leaf);
+        path
+    }
+
+    /// Finishes profiling and returns the collected statistics. Frames which
+    /// are still on the stack (e.g. when the run was terminated) are closed.
+    pub fn finish(&mut self, with_collapsed_stacks: bool) -> Profile {
+        while !self.stack.is_empty() {
+            self.pop_frame(self.last_ops);
+        }
+        let total_msec = self.start.elapsed().as_secs_f64() * 1000.0;
+        let root_msec = (total_msec - self.top_level_children_msec).max(0.0);
+
+        let collapsed_stacks = if with_collapsed_stacks {
+            let mut s = String::new();
+            let root = std::iter::once((ROOT_FRAME_NAME, root_msec));
+            let paths = self.collapsed.iter().map(|(k, v)| (k.as_str(), *v));
+            for (path, msec) in root.chain(paths) {
+                writeln!(&mut s, "{} {}", path, (msec * 1000.0).round() as u64).unwrap();
+            }
+            Some(s)
+        } else {
+            None
+        };
+
+        let mut functions: Vec<_> = self
+            .keys
+            .iter()
+            .zip(&self.stats)
+            .map(|((name, arity), stats)| FunctionProfile {
+                name: name.clone(),
+                arity: *arity,
+                calls: stats.calls,
+                operations: stats.operations,
+                inclusive_msec: stats.inclusive_msec,
+                exclusive_msec: stats.exclusive_msec,
+            })
+            .collect();
+        functions.sort_by(|a, b| b.exclusive_msec.partial_cmp(&a.exclusive_msec).unwrap());
+
+        Profile {
+            functions,
+            collapsed_stacks,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use rhai::{Array, Dynamic, Engine, EvalAltResult, NativeCallContext};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A xoshiro256** pseudo-random number generator. It is small and fast, and
+/// produces the same numbers for the same seed on every platform.
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The state is filled using SplitMix64, as recommended by the authors
+        // of xoshiro, which also avoids the all-zero state.
+        let mut x = seed;
+        let mut state = [0; 4];
+        for s in &mut state {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            *s = z ^ (z >> 31);
+        }
+        Self { state }
+    }
+
+    /// Returns a seed which differs between runs.
+    pub fn entropy_seed() -> u64 {
+        // `performance.now()` alone is coarsened by browsers and starts from
+        // zero in every worker, so it is mixed with the wall-clock time and
+        // the number of seeds returned so far.
+        static COUNT: AtomicU64 = AtomicU64::new(0);
+        let wall_clock_nsec = instant::SystemTime::now()
+            .duration_since(instant::SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        [
+            instant::now().to_bits(),
+            COUNT.fetch_add(1, Ordering::Relaxed),
+        ]
+        .iter()
+        .fold(Self::new(wall_clock_nsec).next_u64(), |hash, &x| {
+            Self::new(hash ^ x).next_u64()
+        })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Returns a number in `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns a number in `0..n` without bias. `n` must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // Reject the top values which would make the lower results more
+        // likely than the others.
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
+
+/// Registers `rand()`, `rand(min, max)`, `rand_float()`, `shuffle(array)`,
+/// `sample(array)` and `seed(n)`, all drawing from `rng`.
+pub fn register_random(engine: &mut Engine, rng: Rc<RefCell<Rng>>) {
+    engine.register_fn("rand", {
+        let rng = rng.clone();
+        move || rng.borrow_mut().next_u64() as i64
+    });
+    engine.register_fn("rand", {
+        let rng = rng.clone();
+        move |context: NativeCallContext, min: i64, max: i64| -> Result<i64, Box<EvalAltResult>> {
+            if min > max {
+                return Err(EvalAltResult::ErrorRuntime(
+                    format!("Invalid range for rand(): {} > {}", min, max).into(),
+                    context.call_position(),
+                )
+                .into());
+            }
+            let span = max.wrapping_sub(min) as u64;
+            let offset = match span.checked_add(1) {
+                Some(n) => rng.borrow_mut().below(n),
+                None => rng.borrow_mut().next_u64(),
+            };
+            Ok(min.wrapping_add(offset as i64))
+        }
+    });
+    engine.register_fn("rand_float", {
+        let rng = rng.clone();
+        move || rng.borrow_mut().next_f64()
+    });
+    engine.register_fn("shuffle", {
+        let rng = rng.clone();
+        move |array: &mut Array| {
+            let mut rng = rng.borrow_mut();
+            for i in (1..array.len()).rev() {
+                let j = rng.below(i as u64 + 1) as usize;
+                array.swap(i, j);
+            }
+        }
+    });
+    engine.register_fn("sample", {
+        let rng = rng.clone();
+        move |array: &mut Array| {
+            if array.is_empty() {
+                Dynamic::UNIT
+            } else {
+                let i = rng.borrow_mut().below(array.len() as u64) as usize;
+                array[i].clone()
+            }
+        }
+    });
+    engine.register_fn("seed", move |seed: i64| {
+        *rng.borrow_mut() = Rng::new(seed as u64);
+    });
+}
@@ -0,0 +1,48 @@
+use rhai::{ASTNode, ParseError, Position, Stmt, AST};
+use std::collections::HashSet;
+
+thread_local! {
+    static ENGINE_FOR_AST_ONLY: rhai::Engine = {
+        let mut engine = rhai::Engine::new();
+        engine.set_optimization_level(rhai::OptimizationLevel::None);
+        engine
+    };
+}
+
+/// Returns the positions of the expression statements in `ast`. The debugger
+/// does not step into these statements, only into their expressions, so they
+/// are told apart from other expressions by position. Similarly, it steps
+/// into a no-op statement when entering a function body.
+pub(crate) fn expr_stmt_positions(ast: &AST) -> HashSet<Position> {
+    let mut positions = HashSet::new();
+    ast.walk(&mut |path: &[ASTNode]| {
+        if let Some(ASTNode::Stmt(Stmt::Expr(expr))) = path.last() {
+            positions.insert(expr.position());
+        }
+        true
+    });
+    positions
+}
+
+/// Compiles a script without optimizations, so that the AST matches the
+/// source code.
+pub(crate) fn compile_unoptimized(script: &str) -> Result<rhai::AST, ParseError> {
+    ENGINE_FOR_AST_ONLY.with(|engine| engine.compile(script))
+}
+
+/// Compiles a script without optimizations and returns the Debug
+/// representation of its AST.
+pub fn dump_ast(script: &str) -> Result<String, ParseError> {
+    compile_unoptimized(script).map(|script_ast| {
+        #[allow(deprecated)]
+        let statements = script_ast.statements();
+        #[allow(deprecated)]
+        let module = script_ast.lib();
+        let mut s = format!("//This is the Debug representation of the AST.\n\n// Statements:\n{:#?}\n\n// Modules (script-defined functions):\n", statements);
+        for f in module.iter_script_fn_info() {
+            use std::fmt::Write;
+            writeln!(&mut s, "{:#?}", &f).unwrap();
+        }
+        s
+    })
+}
@@ -0,0 +1,494 @@
+//! Semantic highlighting: classifies the identifiers of a script using its
+//! AST, so that an editor can color function calls, parameters, constants,
+//! modules, properties and captured variables differently on top of the
+//! highlighting of the mode, which only sees one token at a time.
+
+use crate::highlight::{self, LineStream};
+use crate::scripting;
+use rhai::{ASTFlags, ASTNode, Expr, FnCallExpr, FnPtr, Ident, ParseError, Position, Stmt};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The types of the tokens, named as in the Language Server Protocol.
+pub mod token_types {
+    pub const FUNCTION: &str = "function";
+    pub const METHOD: &str = "method";
+    pub const VARIABLE: &str = "variable";
+    pub const PARAMETER: &str = "parameter";
+    pub const PROPERTY: &str = "property";
+    pub const NAMESPACE: &str = "namespace";
+}
+
+/// The modifiers of the tokens, named as in the Language Server Protocol
+/// except `captured`.
+pub mod token_modifiers {
+    pub const DECLARATION: &str = "declaration";
+    pub const READONLY: &str = "readonly";
+    pub const DEFAULT_LIBRARY: &str = "defaultLibrary";
+    /// A variable of the enclosing scope used in a closure.
+    pub const CAPTURED: &str = "captured";
+}
+
+const MODIFIERS: [&str; 4] = [
+    token_modifiers::DECLARATION,
+    token_modifiers::READONLY,
+    token_modifiers::DEFAULT_LIBRARY,
+    token_modifiers::CAPTURED,
+];
+const DECLARATION: u8 = 1;
+const READONLY: u8 = 1 << 1;
+const DEFAULT_LIBRARY: u8 = 1 << 2;
+const CAPTURED: u8 = 1 << 3;
+
+/// A classified identifier.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticToken {
+    /// 1-based line number.
+    pub line: usize,
+    /// Byte offset of the start of the identifier in the line.
+    pub start: usize,
+    /// Byte offset of the end of the identifier in the line.
+    pub end: usize,
+    /// One of `token_types`.
+    #[serde(rename = "type")]
+    pub token_type: &'static str,
+    /// Some of `token_modifiers`.
+    pub modifiers: Vec<&'static str>,
+}
+
+/// Compiles `script` and returns its classified identifiers, sorted by
+/// position. Identifiers which cannot be classified, e.g. in custom syntax,
+/// are left out.
+///
+/// Scopes are not tracked, so a local variable shadowing a parameter or a
+/// constant is classified as the parameter or the constant.
+pub fn semantic_tokens(script: &str) -> Result<Vec<SemanticToken>, ParseError> {
+    let ast = scripting::compile_unoptimized(script)?;
+    let mut classifier = Classifier::new(script);
+
+    let mut script_fns = HashSet::new();
+    let mut captured_by_closure = HashMap::new();
+    let mut constants = HashSet::new();
+    let mut bound_names = HashSet::new();
+    for f in ast.iter_fn_def() {
+        if !f.name.starts_with("anon$") {
+            script_fns.insert(f.name.to_string());
+        }
+        bound_names.extend(f.params.iter().map(|param| param.to_string()));
+    }
+    ast.walk(&mut |path: &[ASTNode]| {
+        match path.last() {
+            Some(ASTNode::Stmt(Stmt::Var(x, flags, _))) => {
+                if flags.contains(ASTFlags::CONSTANT) {
+                    constants.insert(x.0.name.to_string());
+                }
+                bound_names.insert(x.0.name.to_string());
+            }
+            Some(ASTNode::Stmt(Stmt::For(x, _))) => {
+                bound_names.extend(
+                    std::iter::once(&x.0)
+                        .chain(&x.1)
+                        .map(|ident| ident.name.to_string()),
+                );
+            }
+            Some(ASTNode::Expr(Expr::FnCall(call, _))) => {
+                if let Some((closure, captured)) = curried_closure(call) {
+                    captured_by_closure.insert(closure, captured.len());
+                }
+            }
+            _ => {}
+        }
+        true
+    });
+    let globals = Scope {
+        script_fns: &script_fns,
+        constants: &constants,
+        bound_names: &bound_names,
+        params: &[],
+        captured: &[],
+    };
+
+    #[allow(deprecated)]
+    classifier.walk(ast.statements(), &globals);
+    for f in ast.iter_fn_def() {
+        // The variables captured by a closure are passed before its own
+        // parameters.
+        let captured_count = captured_by_closure.get(f.name.as_str()).copied();
+        let (captured, params) = f.params.split_at(captured_count.unwrap_or(0));
+        let scope = Scope {
+            params,
+            captured,
+            ..globals
+        };
+        classifier.walk(f.body.statements(), &scope);
+    }
+    classifier.classify_declarations_in_tokens();
+    Ok(classifier.into_semantic_tokens())
+}
+
+/// Returns the name of the closure and the captured variables if `call`
+/// binds the variables captured by a closure.
+fn curried_closure(call: &FnCallExpr) -> Option<(String, &[Expr])> {
+    if call.name != "curry" || !call.namespace.is_empty() {
+        return None;
+    }
+    let (first, captured) = call.args.split_first()?;
+    match first {
+        Expr::DynamicConstant(value, _) => {
+            let fn_ptr = value.read_lock::<FnPtr>()?;
+            if fn_ptr.is_anonymous() {
+                Some((fn_ptr.fn_name().to_string(), captured))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// What is known about the names used in the body of the main script or of a
+/// function.
+#[derive(Clone, Copy)]
+struct Scope<'a> {
+    script_fns: &'a HashSet<String>,
+    constants: &'a HashSet<String>,
+    /// Names of all the variables, constants and parameters, which can hold
+    /// function pointers called like functions.
+    bound_names: &'a HashSet<String>,
+    params: &'a [rhai::ImmutableString],
+    captured: &'a [rhai::ImmutableString],
+}
+
+/// A significant token of the script, read with the highlighter.
+struct SourceToken<'a> {
+    /// 1-based line number.
+    line: usize,
+    start: usize,
+    end: usize,
+    /// 1-based column in characters, as in Rhai positions.
+    column: usize,
+    text: &'a str,
+    style: &'static str,
+}
+
+impl SourceToken<'_> {
+    fn is_identifier(&self) -> bool {
+        matches!(self.style, "variable" | "def")
+    }
+}
+
+/// How many tokens after a position are searched for the name of the node at
+/// that position, e.g. for `m::f` whose position is the one of `m`.
+const MAX_LOOKAHEAD: usize = 8;
+
+struct Classifier<'a> {
+    tokens: Vec<SourceToken<'a>>,
+    /// The type and the modifiers of the classified tokens, by index.
+    classes: BTreeMap<usize, (&'static str, u8)>,
+}
+
+impl<'a> Classifier<'a> {
+    fn new(script: &'a str) -> Self {
+        let mut state = highlight::State::default();
+        let mut tokens = Vec::new();
+        for (index, line) in script.split('\n').enumerate() {
+            let mut start = 0;
+            for token in highlight::tokenize_line(&mut LineStream::new(line), &mut state) {
+                let text = line[start..token.end].trim_start();
+                let token_start = token.end - text.len();
+                start = token.end;
+                let style = match token.style {
+                    Some("comment") | None => continue,
+                    Some(_) if text.is_empty() => continue,
+                    Some(style) => style,
+                };
+                tokens.push(SourceToken {
+                    line: index + 1,
+                    start: token_start,
+                    end: token.end,
+                    column: line[..token_start].chars().count() + 1,
+                    text,
+                    style,
+                });
+            }
+        }
+        Self {
+            tokens,
+            classes: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the index of the identifier `name` at or just after `pos`.
+    fn find(&self, pos: Position, name: &str) -> Option<usize> {
+        let (line, column) = (pos.line()?, pos.position()?);
+        let first = self
+            .tokens
+            .partition_point(|token| (token.line, token.column) < (line, column));
+        self.find_from(first, name)
+    }
+
+    fn find_from(&self, first: usize, name: &str) -> Option<usize> {
+        let line = self.tokens.get(first)?.line;
+        self.tokens[first..]
+            .iter()
+            .take(MAX_LOOKAHEAD)
+            .take_while(|token| token.line == line)
+            // Functions like `print` and `call` are highlighted as keywords.
+            .position(|token| {
+                (token.is_identifier() || token.style == "keyword") && token.text == name
+            })
+            .map(|offset| first + offset)
+    }
+
+    /// Records the class of a token. A declaration keeps its type when the
+    /// same token is also seen as a use, e.g. the variable of a `catch`.
+    fn set(&mut self, index: usize, token_type: &'static str, modifiers: u8) {
+        let class = self.classes.entry(index).or_insert((token_type, 0));
+        if modifiers & DECLARATION != 0 || class.1 & DECLARATION == 0 {
+            class.0 = token_type;
+        }
+        class.1 |= modifiers;
+    }
+
+    fn set_at(&mut self, pos: Position, name: &str, token_type: &'static str, modifiers: u8) {
+        if let Some(index) = self.find(pos, name) {
+            self.set(index, token_type, modifiers);
+        }
+    }
+
+    fn set_ident(&mut self, ident: &Ident, token_type: &'static str, modifiers: u8) {
+        self.set_at(ident.pos, &ident.name, token_type, modifiers);
+    }
+
+    /// Classifies the modules of a qualified name starting at `pos`, then the
+    /// name itself.
+    fn set_qualified(
+        &mut self,
+        pos: Position,
+        namespace: &rhai::Namespace,
+        name: &str,
+        token_type: &'static str,
+        modifiers: u8,
+    ) {
+        let mut next = None;
+        for module in namespace.path.iter() {
+            next = self.find(module.pos, &module.name);
+            if let Some(index) = next {
+                self.set(index, token_types::NAMESPACE, 0);
+            }
+        }
+        let index = match next {
+            Some(index) => self.find_from(index + 1, name),
+            None if namespace.is_empty() => self.find(pos, name),
+            None => None,
+        };
+        if let Some(index) = index {
+            self.set(index, token_type, modifiers);
+        }
+    }
+
+    fn set_call(&mut self, call: &FnCallExpr, pos: Position, scope: &Scope) {
+        if call.op_token.is_some() {
+            return;
+        }
+        if let Some((_, captured)) = curried_closure(call) {
+            for arg in captured {
+                if let Expr::Variable(x, _, pos) = arg {
+                    self.set_at(*pos, &x.1, token_types::VARIABLE, CAPTURED);
+                }
+            }
+            return;
+        }
+        let is_default_library = call.namespace.is_empty()
+            && !scope.script_fns.contains(call.name.as_str())
+            && !scope.bound_names.contains(call.name.as_str());
+        let modifiers = if is_default_library {
+            DEFAULT_LIBRARY
+        } else {
+            0
+        };
+        self.set_qualified(
+            pos,
+            &call.namespace,
+            &call.name,
+            token_types::FUNCTION,
+            modifiers,
+        );
+    }
+
+    fn walk(&mut self, statements: &[Stmt], scope: &Scope) {
+        let mut path = Vec::new();
+        for stmt in statements {
+            stmt.walk(&mut path, &mut |path: &[ASTNode]| {
+                match path.last() {
+                    Some(ASTNode::Stmt(stmt)) => self.on_stmt(stmt, scope),
+                    Some(ASTNode::Expr(expr)) => self.on_expr(expr, scope),
+                    _ => {}
+                }
+                true
+            });
+        }
+    }
+
+    fn on_stmt(&mut self, stmt: &Stmt, scope: &Scope) {
+        match stmt {
+            Stmt::Var(x, flags, _) => {
+                let readonly = if flags.contains(ASTFlags::CONSTANT) {
+                    READONLY
+                } else {
+                    0
+                };
+                self.set_ident(&x.0, token_types::VARIABLE, DECLARATION | readonly);
+            }
+            Stmt::For(x, _) => {
+                for ident in std::iter::once(&x.0).chain(&x.1) {
+                    self.set_ident(ident, token_types::VARIABLE, DECLARATION);
+                }
+            }
+            Stmt::TryCatch(x, _) => {
+                if let Expr::Variable(v, _, pos) = &x.expr {
+                    self.set_at(*pos, &v.1, token_types::VARIABLE, DECLARATION);
+                }
+            }
+            Stmt::Import(x, _) => self.set_ident(&x.1, token_types::NAMESPACE, DECLARATION),
+            Stmt::Export(x, _) => self.set_ident(&x.0, token_types::VARIABLE, 0),
+            Stmt::FnCall(call, pos) => self.set_call(call, *pos, scope),
+            _ => {}
+        }
+    }
+
+    fn on_expr(&mut self, expr: &Expr, scope: &Scope) {
+        match expr {
+            Expr::Variable(x, _, pos) => {
+                let name = &x.1;
+                let (token_type, modifiers) = if scope.params.contains(name) {
+                    (token_types::PARAMETER, 0)
+                } else if scope.captured.contains(name) {
+                    (token_types::VARIABLE, CAPTURED)
+                } else if scope.constants.contains(name.as_str()) {
+                    (token_types::VARIABLE, READONLY)
+                } else {
+                    (token_types::VARIABLE, 0)
+                };
+                self.set_qualified(*pos, &x.2, name, token_type, modifiers);
+            }
+            Expr::FnCall(call, pos) => self.set_call(call, *pos, scope),
+            Expr::MethodCall(call, pos) => {
+                self.set_at(*pos, &call.name, token_types::METHOD, 0);
+            }
+            Expr::Property(x, pos) => self.set_at(*pos, &x.2, token_types::PROPERTY, 0),
+            Expr::Map(x, _) => {
+                for (key, _) in &x.0 {
+                    self.set_ident(key, token_types::PROPERTY, DECLARATION);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Classifies the names and the parameters of functions and the
+    /// parameters of closures, which have no positions in the AST.
+    fn classify_declarations_in_tokens(&mut self) {
+        let mut i = 0;
+        let mut after_closure_params = false;
+        while i < self.tokens.len() {
+            let token = &self.tokens[i];
+            if token.style == "keyword" && token.text == "fn" {
+                i = self.classify_fn_declaration(i + 1);
+                after_closure_params = false;
+            } else if token.text == "|" && (after_closure_params || self.may_start_closure(i)) {
+                match self.classify_closure_params(i + 1) {
+                    Some(end) => {
+                        i = end + 1;
+                        after_closure_params = true;
+                    }
+                    None => {
+                        i += 1;
+                        after_closure_params = false;
+                    }
+                }
+            } else {
+                i += 1;
+                after_closure_params = false;
+            }
+        }
+    }
+
+    /// Whether the `|` at `index` is where an operand is expected, i.e. opens
+    /// the parameters of a closure rather than being a binary operator.
+    fn may_start_closure(&self, index: usize) -> bool {
+        match index.checked_sub(1).map(|previous| &self.tokens[previous]) {
+            None => true,
+            Some(previous) => matches!(
+                previous.text,
+                "(" | "[" | "{" | "#{" | "," | "=" | ":" | ";" | "=>" | "return" | "throw" | "in"
+            ),
+        }
+    }
+
+    /// Classifies `name(params)` from `first`, the token after `fn`, and
+    /// returns the index of the token after the parameters.
+    fn classify_fn_declaration(&mut self, first: usize) -> usize {
+        let Some(paren) = (first..self.tokens.len().min(first + MAX_LOOKAHEAD))
+            .find(|&i| self.tokens[i].text == "(")
+        else {
+            return first;
+        };
+        if paren > first && self.tokens[paren - 1].is_identifier() {
+            self.set(paren - 1, token_types::FUNCTION, DECLARATION);
+        }
+        let mut i = paren + 1;
+        while i < self.tokens.len() && self.tokens[i].text != ")" {
+            if self.tokens[i].is_identifier() {
+                self.set(i, token_types::PARAMETER, DECLARATION);
+            }
+            i += 1;
+        }
+        i
+    }
+
+    /// Classifies the parameters of a closure from `first`, the token after
+    /// the opening `|`, and returns the index of the closing `|`, or `None`
+    /// if the tokens are not closure parameters.
+    fn classify_closure_params(&mut self, first: usize) -> Option<usize> {
+        let mut end = first;
+        loop {
+            let token = self.tokens.get(end)?;
+            match token.text {
+                "|" => break,
+                "," => {}
+                _ if token.is_identifier() => {}
+                _ => return None,
+            }
+            end += 1;
+        }
+        for i in first..end {
+            if self.tokens[i].is_identifier() {
+                self.set(i, token_types::PARAMETER, DECLARATION);
+            }
+        }
+        Some(end)
+    }
+
+    fn into_semantic_tokens(self) -> Vec<SemanticToken> {
+        let tokens = self.tokens;
+        self.classes
+            .into_iter()
+            .map(|(index, (token_type, modifiers))| {
+                let token = &tokens[index];
+                SemanticToken {
+                    line: token.line,
+                    start: token.start,
+                    end: token.end,
+                    token_type,
+                    modifiers: MODIFIERS
+                        .iter()
+                        .enumerate()
+                        .filter(|(bit, _)| modifiers & (1 << bit) != 0)
+                        .map(|(_, &modifier)| modifier)
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,243 @@
+use crate::scripting::expr_stmt_positions;
+use rhai::debugger::DebuggerEvent;
+use rhai::{ASTFlags, ASTNode, EvalContext, Expr, Position, Stmt, AST};
+use std::collections::HashSet;
+
+/// Number of trace events recorded when `RunOptions::max_trace_events` is not
+/// specified.
+pub const DEFAULT_MAX_TRACE_EVENTS: usize = 10_000;
+
+#[derive(Debug, serde::Serialize)]
+#[serde(
+    tag = "event",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum TraceEvent {
+    /// A statement is about to be executed.
+    Statement {
+        line: Option<usize>,
+        column: Option<usize>,
+        kind: &'static str,
+        /// Number of function calls on the call stack.
+        depth: usize,
+    },
+    /// A variable has been defined or assigned to. For assignments to an
+    /// element or a property, this is the new value of the whole variable.
+    Variable {
+        line: Option<usize>,
+        column: Option<usize>,
+        name: String,
+        is_definition: bool,
+        value: String,
+        type_name: String,
+    },
+}
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trace {
+    pub events: Vec<TraceEvent>,
+    /// Whether events were dropped after reaching the event cap.
+    pub is_truncated: bool,
+}
+
+/// A variable definition or assignment which has been stepped into but not
+/// yet executed.
+struct PendingVariable {
+    pos: Position,
+    name: String,
+    /// For definitions, the index in the scope the variable will occupy.
+    definition_index: Option<usize>,
+    depth: usize,
+}
+
+pub struct TraceRecorder {
+    expr_stmt_positions: HashSet<Position>,
+    events: Vec<TraceEvent>,
+    max_events: usize,
+    is_truncated: bool,
+    pending: Vec<PendingVariable>,
+}
+
+impl TraceRecorder {
+    pub fn new(ast: &AST, max_events: usize) -> Self {
+        Self {
+            expr_stmt_positions: expr_stmt_positions(ast),
+            events: Vec::new(),
+            max_events,
+            is_truncated: false,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Records the trace events.
+    pub fn on_debugger_event(
+        &mut self,
+        context: &EvalContext,
+        event: &DebuggerEvent,
+        node: ASTNode,
+    ) {
+        if self.is_truncated {
+            return;
+        }
+        let depth = context.global_runtime_state().debugger().call_stack().len();
+
+        // The new value of a variable is only known after the statement has
+        // been executed. A new variable shows up in the scope as soon as it
+        // is defined, while an assignment has to wait for the next statement
+        // on the same call level.
+        let is_expr_stmt = match node {
+            ASTNode::Expr(expr) => self.expr_stmt_positions.contains(&expr.position()),
+            _ => false,
+        };
+        let is_next_stmt = match event {
+            DebuggerEvent::Step => matches!(node, ASTNode::Stmt(_)) || is_expr_stmt,
+            DebuggerEvent::FunctionExitWithValue(_)
+            | DebuggerEvent::FunctionExitWithError(_)
+            | DebuggerEvent::End => true,
+            _ => false,
+        };
+        self.resolve_pending(context, depth, is_next_stmt);
+
+        if !matches!(event, DebuggerEvent::Start | DebuggerEvent::Step) {
+            return;
+        }
+        let (pos, kind) = match node {
+            ASTNode::Stmt(Stmt::Noop(..)) => return,
+            ASTNode::Stmt(stmt) => (stmt.position(), stmt_kind(stmt)),
+            ASTNode::Expr(expr) if is_expr_stmt => (expr.position(), "expression"),
+            _ => return,
+        };
+        self.push(TraceEvent::Statement {
+            line: pos.line(),
+            column: pos.position(),
+            kind,
+            depth,
+        });
+
+        let stmt = match node {
+            ASTNode::Stmt(stmt) => stmt,
+            _ => return,
+        };
+        match stmt {
+            Stmt::Var(x, ..) => self.pending.push(PendingVariable {
+                pos,
+                name: x.0.name.to_string(),
+                definition_index: Some(context.scope().len()),
+                depth,
+            }),
+            Stmt::Assignment(x) => {
+                if let Some(name) = root_variable_name(&x.1.lhs) {
+                    self.pending.push(PendingVariable {
+                        pos,
+                        name: name.to_string(),
+                        definition_index: None,
+                        depth,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_pending(&mut self, context: &EvalContext, depth: usize, is_next_stmt: bool) {
+        while let Some(pending) = self.pending.last() {
+            if pending.depth < depth {
+                break;
+            }
+            if pending.depth > depth {
+                // The function returned before the variable could be seen.
+                self.pending.pop();
+                continue;
+            }
+            let scope = context.scope();
+            let value = match pending.definition_index {
+                // The scope is iterated from the most recent variable.
+                Some(index) if index < scope.len() => scope
+                    .iter_raw()
+                    .nth(scope.len() - 1 - index)
+                    .filter(|(name, ..)| *name == pending.name)
+                    .map(|(.., value)| value),
+                Some(_) => None,
+                None if is_next_stmt => scope.get(&pending.name),
+                None => break,
+            };
+            let value = match value {
+                Some(value) => value,
+                // A variable defined as the last statement of a block is gone
+                // by the next statement, so it is never observable.
+                None if is_next_stmt => {
+                    self.pending.pop();
+                    continue;
+                }
+                None => break,
+            };
+            let event = TraceEvent::Variable {
+                line: pending.pos.line(),
+                column: pending.pos.position(),
+                name: pending.name.clone(),
+                is_definition: pending.definition_index.is_some(),
+                value: format!("{:?}", value),
+                type_name: context
+                    .engine()
+                    .map_type_name(value.type_name())
+                    .to_string(),
+            };
+            self.pending.pop();
+            self.push(event);
+        }
+    }
+
+    fn push(&mut self, event: TraceEvent) {
+        if self.events.len() < self.max_events {
+            self.events.push(event);
+        } else {
+            self.is_truncated = true;
+            self.pending.clear();
+        }
+    }
+
+    pub fn finish(&mut self) -> Trace {
+        Trace {
+            events: std::mem::take(&mut self.events),
+            is_truncated: self.is_truncated,
+        }
+    }
+}
+
+/// Returns the name of the variable modified by assigning to `expr`, which
+/// may be an element or a property of the variable.
+fn root_variable_name(expr: &Expr) -> Option<&str> {
+    match expr {
+        Expr::Variable(x, ..) if x.2.is_empty() => Some(x.1.as_str()),
+        Expr::Index(x, ..) | Expr::Dot(x, ..) => root_variable_name(&x.lhs),
+        _ => None,
+    }
+}
+
+fn stmt_kind(stmt: &Stmt) -> &'static str {
+    match stmt {
+        Stmt::Noop(..) => "noop",
+        Stmt::If(..) => "if",
+        Stmt::Switch(..) => "switch",
+        Stmt::While(..) => "while",
+        Stmt::Do(..) => "do",
+        Stmt::For(..) => "for",
+        Stmt::Var(_, flags, ..) if flags.intersects(ASTFlags::CONSTANT) => "const",
+        Stmt::Var(..) => "let",
+        Stmt::Assignment(..) => "assignment",
+        Stmt::FnCall(..) => "call",
+        Stmt::Block(..) => "block",
+        Stmt::TryCatch(..) => "try",
+        Stmt::Expr(..) => "expression",
+        Stmt::BreakLoop(_, flags, ..) if flags.intersects(ASTFlags::BREAK) => "break",
+        Stmt::BreakLoop(..) => "continue",
+        Stmt::Return(_, flags, ..) if flags.intersects(ASTFlags::BREAK) => "throw",
+        Stmt::Return(..) => "return",
+        Stmt::Import(..) => "import",
+        Stmt::Export(..) => "export",
+        Stmt::Share(..) => "share",
+        _ => "other",
+    }
+}
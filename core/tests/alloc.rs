@@ -0,0 +1,36 @@
+//! Run with `cargo test --features track_alloc`.
+
+#![cfg(feature = "track_alloc")]
+
+mod common;
+
+use common::run;
+use rhai_playground_core::alloc_tracker::TrackingAllocator;
+use rhai_playground_core::RunOptions;
+use std::alloc::System;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator<System> = TrackingAllocator::new(System);
+
+// The figures are global, so both runs are in the same test to keep other
+// tests from allocating in between.
+#[test]
+fn reports_the_allocations_of_each_run() {
+    let script = "let a = []; for i in 0..10000 { a.push(`item ${i}`); } a.len()";
+    let big = run(script, &RunOptions::default()).1.unwrap().alloc;
+    assert!(big.allocations >= 10000, "{:?}", big);
+    assert!(big.frees > 0, "{:?}", big);
+    assert!(big.peak_bytes > big.start_bytes, "{:?}", big);
+    assert!(big.peak_bytes >= big.end_bytes, "{:?}", big);
+
+    // The peak is reset at the start of every run.
+    let small = run("1 + 1", &RunOptions::default()).1.unwrap().alloc;
+    assert!(small.allocations > 0, "{:?}", small);
+    assert!(small.allocations < big.allocations / 10, "{:?}", small);
+    assert!(
+        small.peak_bytes - small.start_bytes < big.peak_bytes - big.start_bytes,
+        "{:?} {:?}",
+        small,
+        big
+    );
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::run_on;
+use rhai_playground_core::{Playground, RunOptions};
+
+#[test]
+fn benchmarks_have_the_functions_of_runs() {
+    let mut playground = Playground::new();
+    let result = playground
+        .benchmark("let x = rand(1, 6); to_json([x, now().year])", 3)
+        .unwrap();
+    assert_eq!(result.iterations, 3);
+    assert!(result.min_msec <= result.median_msec && result.median_msec <= result.max_msec);
+    assert!(result.operations > 0);
+}
+
+#[test]
+fn benchmarks_do_not_depend_on_earlier_runs() {
+    let mut playground = Playground::new();
+    let options = RunOptions {
+        input_lines: Some(vec!["a".into(), "b".into()]),
+        ..Default::default()
+    };
+    let (_, result) = run_on(&mut playground, "input()", &options);
+    assert_eq!(result.unwrap().result, "a");
+
+    let err = playground.benchmark("input()", 1).unwrap_err();
+    assert!(err.contains("No more input"), "{}", err);
+}
+
+#[test]
+fn rejects_zero_iterations() {
+    assert!(Playground::new().benchmark("1", 0).is_err());
+}
@@ -0,0 +1,37 @@
+//! Helpers shared by the integration tests which run scripts.
+
+#![allow(dead_code)]
+
+use rhai_playground_core::{Playground, RunError, RunOptions, RunResult};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Runs `script` on `playground` and returns the output, one entry per line,
+/// and the result.
+pub fn run_on(
+    playground: &mut Playground,
+    script: &str,
+    options: &RunOptions,
+) -> (Vec<String>, Result<RunResult, RunError>) {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let result = playground.run_script(
+        script,
+        options,
+        {
+            let output = output.clone();
+            move |s| {
+                output
+                    .borrow_mut()
+                    .extend(s.split('\n').map(str::to_string))
+            }
+        },
+        |_| {},
+    );
+    let output = output.take();
+    (output, result)
+}
+
+/// Runs `script` on a new playground.
+pub fn run(script: &str, options: &RunOptions) -> (Vec<String>, Result<RunResult, RunError>) {
+    run_on(&mut Playground::new(), script, options)
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::RunOptions;
+
+#[test]
+fn counts_executed_lines() {
+    let script = "fn f(x) {
+    x + 1
+}
+let a = [];
+for i in 0..3 {
+    a.push(f(i));
+}
+if a.len() > 5 {
+    print(a);
+}";
+    let options = RunOptions {
+        coverage: true,
+        ..Default::default()
+    };
+    let (_, result) = run(script, &options);
+    let coverage = result.unwrap().coverage.unwrap();
+    let hits: Vec<_> = coverage.lines.iter().map(|l| (l.line, l.hits)).collect();
+    assert_eq!(
+        hits,
+        [(1, 3), (2, 3), (4, 1), (5, 1), (6, 3), (8, 1), (9, 0)]
+    );
+    assert!(coverage.lines[4].operations > 0);
+    assert_eq!(coverage.lines[6].operations, 0);
+}
+
+#[test]
+fn covers_only_on_request() {
+    let (_, result) = run("1", &RunOptions::default());
+    assert!(result.unwrap().coverage.is_none());
+}
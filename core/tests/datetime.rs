@@ -0,0 +1,96 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::{DateTime, RunOptions};
+
+fn fixed_clock(msec: i64) -> RunOptions {
+    RunOptions {
+        fixed_time_msec: Some(msec),
+        ..Default::default()
+    }
+}
+
+fn eval(script: &str, options: &RunOptions) -> String {
+    match run(script, options).1 {
+        Ok(result) => result.result,
+        Err(err) => panic!("{}: {}", script, err),
+    }
+}
+
+#[test]
+fn a_fixed_clock_makes_runs_deterministic() {
+    let options = fixed_clock(1_614_834_367_089);
+    let script = "let t = now(); [t.year, t.month, t.day, t.weekday, t.to_iso_string()]";
+    assert_eq!(
+        eval(script, &options),
+        r#"[2021, 3, 4, 4, "2021-03-04T05:06:07.089Z"]"#
+    );
+    assert_eq!(eval("let a = timestamp(); a.elapsed", &options), "0.0");
+    assert_eq!(eval("now()", &options), eval("now()", &options));
+}
+
+#[test]
+fn timestamps_support_the_built_in_operations() {
+    let options = fixed_clock(0);
+    let script = "let a = timestamp();
+        let b = a + 1.5;
+        let c = a;
+        c += 2;
+        c -= 1;
+        [b - a, a - b, a <= b, a < b, b > a, b >= c, a == c - 1, a != b, a.elapsed(), (b - 1.5).elapsed]";
+    assert_eq!(
+        eval(script, &options),
+        "[1.5, -1.5, true, true, true, true, true, true, 0.0, 0.0]"
+    );
+    assert_eq!(eval("`${timestamp()}`", &options), "<timestamp>");
+
+    let (_, result) = run("let t = timestamp();\n(t + 1).elapsed", &options);
+    let message = result.unwrap_err().message;
+    assert!(message.contains("later than now"), "{}", message);
+    assert!(message.contains("line 2"), "{}", message);
+}
+
+#[test]
+fn date_times_can_be_compared_and_subtracted() {
+    let options = fixed_clock(0);
+    let script = r#"let a = parse_date("2020-01-01"); let b = parse_date("2020-01-02T00:00:01.5Z");
+        [b - a, a < b, a <= b, a <= a, b > a, b >= a, b >= b, a >= b, a == parse_date("2020-01-01T00:00")]"#;
+    assert_eq!(
+        eval(script, &options),
+        "[86401500, true, true, true, true, true, true, false, true]"
+    );
+}
+
+#[test]
+fn parses_iso_8601() {
+    let parse = |s| DateTime::parse_iso(s).map(DateTime::to_iso_string);
+    assert_eq!(
+        parse("2020-02-29").as_deref(),
+        Some("2020-02-29T00:00:00.000Z")
+    );
+    assert_eq!(
+        parse("2020-02-29 23:59:59.1234").as_deref(),
+        Some("2020-02-29T23:59:59.123Z")
+    );
+    assert_eq!(
+        parse("2020-03-01T01:30+02:00").as_deref(),
+        Some("2020-02-29T23:30:00.000Z")
+    );
+    assert_eq!(
+        parse("2020-02-29T23:30-00:30").as_deref(),
+        Some("2020-03-01T00:00:00.000Z")
+    );
+    for invalid in [
+        "2019-02-29",
+        "2020-13-01",
+        "2020-01-01T24:00",
+        "2020-01-01T00:60",
+        "2020-01-01T00:00+24:00",
+        "2020-01-01T00:00+99:99",
+        "2020-01-01T00:00+01:60",
+        "2020-01-01T00:00Zx",
+        "20-01-01",
+    ] {
+        assert_eq!(parse(invalid), None, "{}", invalid);
+    }
+}
@@ -0,0 +1,107 @@
+//! Runs every script in `example-scripts` and compares the output and the
+//! result with the files in `tests/expected`.
+//!
+//! After a change that is meant to alter the output, e.g. a Rhai upgrade,
+//! regenerate the expected files and review the diff:
+//!
+//! ```sh
+//! UPDATE_EXPECTED=1 cargo test -p rhai-playground-core --test examples
+//! ```
+
+use rhai_playground_core::{Playground, RunOptions};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The seed and the time are fixed so that scripts using `rand()` or
+/// `timestamp()` produce the same output on every run.
+fn run_options() -> RunOptions {
+    RunOptions {
+        random_seed: Some(0),
+        fixed_time_msec: Some(0),
+        ..Default::default()
+    }
+}
+
+/// Runs `script` and returns the output lines followed by the result or the
+/// error.
+fn run_example(script: &str) -> String {
+    let output = Rc::new(RefCell::new(String::new()));
+    let result = Playground::new().run_script(
+        script,
+        &run_options(),
+        {
+            let output = output.clone();
+            move |s| {
+                let mut output = output.borrow_mut();
+                output.push_str(s);
+                output.push('\n');
+            }
+        },
+        |_| {},
+    );
+    let mut output = output.take();
+    match result {
+        Ok(result) => output.push_str(&format!("=> {}\n", result.result)),
+        Err(err) => output.push_str(&format!("EXCEPTION: {}\n", err)),
+    }
+    output
+}
+
+fn example_scripts() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../example-scripts");
+    let mut scripts: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|err| panic!("Cannot read {}: {}", dir.display(), err))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+#[test]
+fn example_scripts_match_expected_output() {
+    let expected_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/expected");
+    let update = std::env::var_os("UPDATE_EXPECTED").is_some();
+    let scripts = example_scripts();
+    assert!(!scripts.is_empty(), "No example scripts found");
+
+    let mut failures = Vec::new();
+    for path in &scripts {
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let expected_path = expected_dir.join(format!("{}.out", name));
+        let script = fs::read_to_string(path).unwrap();
+        let actual = run_example(&script);
+
+        if update {
+            fs::create_dir_all(&expected_dir).unwrap();
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected.replace("\r\n", "\n") == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{}: the output differs from {}\n--- expected\n{}--- actual\n{}",
+                name,
+                expected_path.display(),
+                expected,
+                actual
+            )),
+            Err(err) => failures.push(format!(
+                "{}: cannot read {}: {}",
+                name,
+                expected_path.display(),
+                err
+            )),
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} examples failed (run with UPDATE_EXPECTED=1 to regenerate the expected files):\n\n{}",
+        failures.len(),
+        scripts.len(),
+        failures.join("\n")
+    );
+}
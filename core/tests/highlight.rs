@@ -0,0 +1,292 @@
+use proptest::prelude::*;
+use rhai_playground_core::highlight::{
+    fold_ranges, indent, to_utf16_offsets, token, tokenize_line, CharStream, LineStream, State,
+};
+
+/// Highlights `lines` like an editor would, returning the text and the style
+/// of each token.
+fn highlight(lines: &[&str]) -> Vec<Vec<(String, &'static str)>> {
+    let mut state = State::default();
+    lines
+        .iter()
+        .map(|line| {
+            let mut start = 0;
+            tokenize_line(&mut LineStream::new(line), &mut state)
+                .into_iter()
+                .filter_map(|token| {
+                    let text = line[start..token.end].trim().to_string();
+                    start = token.end;
+                    token.style.map(|style| (text, style))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn tokens(pairs: &[(&str, &'static str)]) -> Vec<(String, &'static str)> {
+    pairs
+        .iter()
+        .map(|&(s, style)| (s.to_string(), style))
+        .collect()
+}
+
+#[test]
+fn highlights_definitions_and_keywords() {
+    let lines = highlight(&["fn add(a, b) {", "    let x = a + 1.5; // sum", "}"]);
+    assert_eq!(
+        lines[0],
+        tokens(&[
+            ("fn", "keyword"),
+            ("add", "def"),
+            ("(", "bracket"),
+            ("a", "def"),
+            (",", "operator"),
+            ("b", "def"),
+            (")", "bracket"),
+            ("{", "bracket"),
+        ])
+    );
+    assert_eq!(
+        lines[1],
+        tokens(&[
+            ("let", "keyword"),
+            ("x", "def"),
+            ("=", "operator"),
+            ("a", "variable"),
+            ("+", "operator"),
+            ("1.5", "number"),
+            (";", "operator"),
+            ("// sum", "comment"),
+        ])
+    );
+    assert_eq!(lines[2], tokens(&[("}", "bracket")]));
+}
+
+#[test]
+fn highlights_all_binding_sites() {
+    let definitions = |lines: &[&str]| -> Vec<String> {
+        highlight(lines)
+            .into_iter()
+            .flatten()
+            .filter(|(_, style)| *style == "def")
+            .map(|(text, _)| text)
+            .collect()
+    };
+    assert_eq!(
+        definitions(&["fn f(a, /* b */", "    b) { a | b }"]),
+        ["f", "a", "b"]
+    );
+    assert_eq!(
+        definitions(&["let g = |x, y| x | y;", "h(|| 1, |z| |w| z);"]),
+        ["g", "x", "y", "z", "w"]
+    );
+    assert_eq!(
+        definitions(&["for (item, index) in list {}", "for i in 0..9 {}"]),
+        ["item", "index", "i"]
+    );
+    assert_eq!(
+        definitions(&["try {} catch (err) { err }", "try {} catch { e }"]),
+        ["err"]
+    );
+    assert_eq!(
+        definitions(&["import \"m\" as m;", "const K = (a) | (b);"]),
+        ["m", "K"]
+    );
+}
+
+#[test]
+fn keeps_state_across_lines() {
+    let lines = highlight(&["/* a", "b */ 42", "`x = ${", "1 + 2} y` true"]);
+    assert_eq!(lines[0], tokens(&[("/* a", "comment")]));
+    assert_eq!(lines[1], tokens(&[("b */", "comment"), ("42", "number")]));
+    assert_eq!(lines[2], tokens(&[("`x = $", "string"), ("{", "bracket")]));
+    assert_eq!(
+        lines[3],
+        tokens(&[
+            ("1", "number"),
+            ("+", "operator"),
+            ("2", "number"),
+            ("}", "bracket"),
+            ("y`", "string"),
+            ("true", "builtin"),
+        ])
+    );
+}
+
+#[test]
+fn characters_put_back_by_the_tokenizer_start_the_next_token() {
+    let lines = highlight(&["1..2", "x !in y", "é1."]);
+    assert_eq!(
+        lines[0],
+        tokens(&[("1", "number"), ("..", "operator"), ("2", "number")])
+    );
+    assert_eq!(
+        lines[1],
+        tokens(&[("x", "variable"), ("!in", "keyword"), ("y", "variable")])
+    );
+    assert_eq!(lines[2].last().unwrap().0, ".");
+}
+
+#[test]
+fn converts_offsets_to_utf16() {
+    let line = "let s = \"😃é\"; // ❤ x";
+    let byte_ends: Vec<_> = tokenize_line(&mut LineStream::new(line), &mut State::default())
+        .iter()
+        .map(|token| token.end)
+        .collect();
+    let mut tokens = tokenize_line(&mut LineStream::new(line), &mut State::default());
+    to_utf16_offsets(line, &mut tokens);
+    for (token, byte_end) in tokens.iter().zip(byte_ends) {
+        assert_eq!(token.end, line[..byte_end].encode_utf16().count());
+    }
+    assert_eq!(tokens.last().unwrap().end, line.encode_utf16().count());
+}
+
+#[test]
+fn states_differ_only_inside_multi_line_tokens() {
+    let state_after = |line: &str| {
+        let mut state = State::default();
+        tokenize_line(&mut LineStream::new(line), &mut state);
+        state
+    };
+    assert_eq!(state_after("let x = 1;"), state_after("let y = 2;"));
+    assert_ne!(state_after("let x = 1;"), state_after("let x = /* 1;"));
+    assert_ne!(state_after("let x = 1;"), state_after("let x = `${"));
+}
+
+#[test]
+fn folds_brackets_spanning_lines() {
+    let text = "fn f() {\n    let m = #{\n        a: [1,\n            2],\n    }; // }\n    `${\n    1}`\n}";
+    let ranges: Vec<_> = fold_ranges(text)
+        .iter()
+        .map(|range| &text[range.from..range.to])
+        .collect();
+    assert_eq!(
+        ranges,
+        [
+            &text[8..text.len() - 1],
+            "\n        a: [1,\n            2],\n    ",
+            "1,\n            2",
+            "\n    1",
+        ]
+    );
+}
+
+#[test]
+fn indents_after_unclosed_brackets() {
+    let mut state = State::default();
+    let mut stream = LineStream::new("    if x {");
+    while !stream.eol() {
+        token(&mut stream, &mut state);
+    }
+    assert_eq!(indent(&state, 4, ""), Some(8));
+    assert_eq!(indent(&state, 4, "}"), Some(4));
+}
+
+/// Pieces of Rhai code which start or end the multi-line constructs of the
+/// mode, mixed with arbitrary characters.
+fn fragments() -> impl Strategy<Value = String> {
+    let fragment = prop_oneof![
+        Just("`"),
+        Just("${"),
+        Just("}"),
+        Just("{"),
+        Just("\""),
+        Just("'"),
+        Just("\\"),
+        Just("/*"),
+        Just("*/"),
+        Just("//"),
+        Just("///"),
+        Just("\n"),
+        Just(" "),
+        Just("\t"),
+        Just("#{"),
+        Just("0x"),
+        Just("1."),
+        Just("1e"),
+        Just(".."),
+        Just("!in"),
+        Just("fn "),
+        Just("let "),
+        Just("x"),
+        Just("😃"),
+    ]
+    .prop_map(str::to_string);
+    let text = prop_oneof![fragment, any::<char>().prop_map(String::from)];
+    prop::collection::vec(text, 0..64).prop_map(|v| v.concat())
+}
+
+/// Highlights `line` and checks that every call to `token` reads at least one
+/// character, like CodeMirror requires, until the whole line is read.
+fn check_line(
+    stream: &mut impl CharStream,
+    state: &mut State,
+    len: usize,
+) -> Result<(), TestCaseError> {
+    while !stream.eol() {
+        let start = stream.pos();
+        token(stream, state);
+        prop_assert!(
+            stream.pos() > start,
+            "no progress at offset {} of {}",
+            start,
+            len
+        );
+    }
+    prop_assert_eq!(stream.pos(), len);
+    let _ = indent(state, 4, "}");
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn tokens_cover_every_line(text in fragments()) {
+        let mut state = State::default();
+        for line in text.split('\n') {
+            let mut stream = LineStream::new(line);
+            check_line(&mut stream, &mut state, line.len())?;
+        }
+    }
+
+    #[test]
+    fn utf16_offsets_cover_every_line(units in prop::collection::vec(any::<u16>(), 0..64)) {
+        check_utf16_lines(&units)?;
+    }
+
+    #[test]
+    fn unpaired_surrogates_take_one_unit(
+        text in fragments(),
+        surrogate in 0xd800u16..0xe000,
+        at in any::<prop::sample::Index>(),
+    ) {
+        let mut units: Vec<u16> = text.encode_utf16().collect();
+        units.insert(at.index(units.len() + 1), surrogate);
+        check_utf16_lines(&units)?;
+    }
+}
+
+/// Highlights text from JavaScript like the editor does: the bindings replace
+/// unpaired surrogates with `REPLACEMENT_CHARACTER`, and the offsets are
+/// converted back to UTF-16 afterwards. They must still end at the end of
+/// each line of the original text.
+fn check_utf16_lines(units: &[u16]) -> Result<(), TestCaseError> {
+    let mut state = State::default();
+    for units in units.split(|&unit| unit == u16::from(b'\n')) {
+        let line = String::from_utf16_lossy(units);
+        let mut tokens = tokenize_line(&mut LineStream::new(&line), &mut state);
+        to_utf16_offsets(&line, &mut tokens);
+        let mut start = 0;
+        for token in &tokens {
+            prop_assert!(
+                token.end > start,
+                "empty token at offset {} of {:?}",
+                start,
+                line
+            );
+            start = token.end;
+        }
+        prop_assert_eq!(start, units.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::{run, run_on};
+use rhai_playground_core::{InputProvider, Playground, RunOptions};
+use std::rc::Rc;
+
+#[test]
+fn reads_the_given_input_lines() {
+    let options = RunOptions {
+        input_lines: Some(vec!["Ann".into()]),
+        ..Default::default()
+    };
+    let (output, result) = run(r#"let name = input("Name? "); `Hi ${name}`"#, &options);
+    assert_eq!(output, ["[INPUT] Name? Ann"]);
+    assert_eq!(result.unwrap().result, "Hi Ann");
+
+    let (_, result) = run("input(); input()", &options);
+    assert!(result.unwrap_err().message.contains("No more input"));
+}
+
+struct Lines(Vec<&'static str>);
+
+impl InputProvider for Lines {
+    fn read_line(&self, prompt: &str) -> Option<String> {
+        self.0
+            .iter()
+            .find(|line| line.starts_with(prompt))
+            .map(|line| line.to_string())
+    }
+}
+
+#[test]
+fn releases_the_functions_of_a_run_when_it_finishes() {
+    let mut playground = Playground::new();
+    let provider = Rc::new(Lines(vec!["a"]));
+    playground.set_input_provider(Some(provider.clone()));
+    let (_, result) = run_on(&mut playground, "input(\"a\")", &RunOptions::default());
+    assert_eq!(result.unwrap().result, "a");
+    assert_eq!(Rc::strong_count(&provider), 2);
+
+    // The input of the run is not kept by the playground.
+    playground.set_input_provider(None);
+    assert_eq!(Rc::strong_count(&provider), 1);
+    let (_, result) = run_on(&mut playground, "input()", &RunOptions::default());
+    assert!(result.is_err());
+}
+
+#[test]
+fn releases_the_output_callback_when_a_run_finishes() {
+    let output = Rc::new(());
+    let mut playground = Playground::new();
+    let result = playground.run_script(
+        "print(1); input()",
+        &RunOptions {
+            input_lines: Some(vec!["x".into()]),
+            ..Default::default()
+        },
+        {
+            let output = output.clone();
+            move |_| {
+                let _ = &output;
+            }
+        },
+        |_| {},
+    );
+    assert!(result.is_ok());
+    assert_eq!(Rc::strong_count(&output), 1);
+}
+
+struct Terminal;
+
+impl InputProvider for Terminal {
+    fn read_line(&self, _prompt: &str) -> Option<String> {
+        Some("typed".into())
+    }
+
+    fn is_echoed(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn echoes_lines_unless_the_provider_shows_them() {
+    let script = r#"print(input("? ")); 1"#;
+    let mut playground = Playground::new();
+    playground.set_input_provider(Some(Rc::new(Lines(vec!["? a"]))));
+    let (output, _) = run_on(&mut playground, script, &RunOptions::default());
+    assert_eq!(output, ["[INPUT] ? ? a", "[PRINT] ? a"]);
+
+    playground.set_input_provider(Some(Rc::new(Terminal)));
+    let (output, _) = run_on(&mut playground, script, &RunOptions::default());
+    assert_eq!(output, ["[PRINT] typed"]);
+}
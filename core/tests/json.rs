@@ -0,0 +1,47 @@
+mod common;
+
+use common::run;
+use rhai::Dynamic;
+use rhai_playground_core::json::to_json;
+use rhai_playground_core::{DateTime, RunOptions};
+
+#[test]
+fn writes_date_times_in_iso_8601() {
+    let value = Dynamic::from(DateTime::from_msec(1_000));
+    assert_eq!(
+        to_json(&value, false).unwrap(),
+        r#""1970-01-01T00:00:01.000Z""#
+    );
+
+    let options = RunOptions {
+        fixed_time_msec: Some(0),
+        json_result: true,
+        ..Default::default()
+    };
+    let (_, result) = run("#{ at: [now()] }", &options);
+    assert_eq!(
+        result.unwrap().result,
+        "{\n  \"at\": [\n    \"1970-01-01T00:00:00.000Z\"\n  ]\n}"
+    );
+}
+
+#[test]
+fn writes_function_pointers_as_their_names() {
+    let (_, result) = run(
+        r#"to_json([Fn("x"), 1, "a", true, ()])"#,
+        &RunOptions::default(),
+    );
+    assert_eq!(result.unwrap().result, r#"["x",1,"a",true,null]"#);
+}
+
+#[test]
+fn parses_any_json_value() {
+    let (_, result) = run(
+        r#"let v = parse_json("[1, {\"a\": null}]"); `${v[0]} ${type_of(v[1].a)} ${parse_json("2")}`"#,
+        &RunOptions::default(),
+    );
+    assert_eq!(result.unwrap().result, "1 () 2");
+
+    let (_, result) = run(r#"parse_json("{")"#, &RunOptions::default());
+    assert!(result.unwrap_err().message.contains("Invalid JSON"));
+}
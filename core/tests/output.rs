@@ -0,0 +1,59 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::{Playground, RunOptions};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[test]
+fn prefixes_print_and_debug_output() {
+    let (output, result) = run(r#"print("a"); debug("b"); 1"#, &RunOptions::default());
+    assert_eq!(
+        output,
+        ["[PRINT] a", r#"[DEBUG] <script>:[line 1, position 13] "b""#]
+    );
+    assert_eq!(result.unwrap().result, "1");
+}
+
+#[test]
+fn batches_lines_printed_in_a_short_time() {
+    let batches = Rc::new(RefCell::new(Vec::new()));
+    let result = Playground::new().run_script(
+        "for i in 0..3 { print(i) }",
+        &RunOptions::default(),
+        {
+            let batches = batches.clone();
+            move |s| batches.borrow_mut().push(s.to_string())
+        },
+        |_| {},
+    );
+    assert!(result.is_ok());
+    assert_eq!(*batches.borrow(), ["[PRINT] 0\n[PRINT] 1\n[PRINT] 2"]);
+}
+
+#[test]
+fn truncates_output_beyond_the_limit() {
+    let options = RunOptions {
+        // Two lines of 9 bytes and their line breaks.
+        max_output_len: Some(20),
+        ..Default::default()
+    };
+    let (output, result) = run("for i in 0..5 { print(i) } 42", &options);
+    assert_eq!(
+        output,
+        [
+            "[PRINT] 0",
+            "[PRINT] 1",
+            "[Output truncated: exceeded the limit of 20 bytes]",
+        ]
+    );
+    // The script still runs to the end.
+    assert_eq!(result.unwrap().result, "42");
+}
+
+#[test]
+fn output_is_unlimited_by_default() {
+    let (output, _) = run("for i in 0..10000 { print(i) }", &RunOptions::default());
+    assert_eq!(output.len(), 10000);
+    assert_eq!(output.last().unwrap(), "[PRINT] 9999");
+}
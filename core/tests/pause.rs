@@ -0,0 +1,99 @@
+mod common;
+
+use common::run_on;
+use rhai_playground_core::{PauseControl, PauseInfo, Playground, RunOptions};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Requests a single pause right away, and resumes after `pause_duration`.
+struct PauseOnce {
+    is_requested: Cell<bool>,
+    pause_duration: Duration,
+    pauses: RefCell<Vec<(u64, Option<usize>)>>,
+}
+
+impl PauseOnce {
+    fn new(pause_duration: Duration) -> Rc<Self> {
+        Rc::new(Self {
+            is_requested: Cell::new(true),
+            pause_duration,
+            pauses: RefCell::new(Vec::new()),
+        })
+    }
+}
+
+impl PauseControl for PauseOnce {
+    fn is_pause_requested(&self) -> bool {
+        self.is_requested.get()
+    }
+
+    fn wait_for_resume(&self, info: &PauseInfo) {
+        self.is_requested.set(false);
+        self.pauses.borrow_mut().push((info.operations, info.line));
+        std::thread::sleep(self.pause_duration);
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+fn playground_with(control: &Rc<PauseOnce>) -> Playground {
+    let mut playground = Playground::new();
+    playground.set_pause_control(Some(control.clone()));
+    playground
+}
+
+const LOOP: &str = "let x = 0;\nfor i in 0..10000 {\n    x += i;\n}\nx";
+
+#[test]
+fn pauses_and_resumes_a_running_script() {
+    let control = PauseOnce::new(Duration::ZERO);
+    let (_, result) = run_on(&mut playground_with(&control), LOOP, &RunOptions::default());
+    assert_eq!(result.unwrap().result, "49995000");
+    let pauses = control.pauses.borrow();
+    assert_eq!(pauses.len(), 1);
+    // Without instrumentation, only the operations count is known.
+    assert!(pauses[0].0 > 0);
+    assert_eq!(pauses[0].1, None);
+}
+
+#[test]
+fn instrumented_runs_pause_at_a_statement() {
+    let control = PauseOnce::new(Duration::ZERO);
+    let options = RunOptions {
+        coverage: true,
+        ..Default::default()
+    };
+    let (_, result) = run_on(&mut playground_with(&control), LOOP, &options);
+    assert_eq!(result.unwrap().result, "49995000");
+    assert_eq!(control.pauses.borrow()[0].1, Some(3));
+}
+
+#[test]
+fn time_spent_paused_does_not_count_towards_the_time_limit() {
+    let control = PauseOnce::new(Duration::from_millis(500));
+    let options = RunOptions {
+        max_duration_msec: Some(300),
+        ..Default::default()
+    };
+    let (_, result) = run_on(&mut playground_with(&control), LOOP, &options);
+    assert_eq!(result.unwrap().result, "49995000");
+    assert_eq!(control.pauses.borrow().len(), 1);
+}
+
+#[test]
+fn sleep_can_be_paused() {
+    let control = PauseOnce::new(Duration::ZERO);
+    let start = Instant::now();
+    let (output, result) = run_on(
+        &mut playground_with(&control),
+        "print(1); sleep(100); 2",
+        &RunOptions::default(),
+    );
+    assert_eq!(result.unwrap().result, "2");
+    assert_eq!(output, ["[PRINT] 1"]);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+    assert_eq!(control.pauses.borrow()[0].1, Some(1));
+}
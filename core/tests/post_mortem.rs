@@ -0,0 +1,96 @@
+mod common;
+
+use common::run;
+use rhai::debugger::DebuggerCommand;
+use rhai::Engine;
+use rhai_playground_core::inspect::{CallFrameInfo, PostMortemRecorder};
+use rhai_playground_core::RunOptions;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn post_mortem() -> RunOptions {
+    RunOptions {
+        post_mortem: true,
+        ..Default::default()
+    }
+}
+
+/// The name and the variables of a frame as `name = value` strings.
+fn describe(frame: &CallFrameInfo) -> (Option<&str>, Vec<String>) {
+    let variables = frame
+        .variables
+        .iter()
+        .map(|v| format!("{} = {}", v.name, v.value.value))
+        .collect();
+    (frame.fn_name.as_deref(), variables)
+}
+
+#[test]
+fn captures_the_call_frames_of_a_failed_script() {
+    let script = r#"fn inner(x) {
+    let y = x * 2;
+    throw "boom";
+}
+fn outer(a) {
+    let b = a + 1;
+    inner(b)
+}
+let top = 1;
+outer(top)"#;
+    let (_, result) = run(script, &post_mortem());
+    let err = result.unwrap_err();
+    assert!(err.message.contains("boom"), "{}", err.message);
+    let frames: Vec<_> = err.frames.iter().map(describe).collect();
+    assert_eq!(
+        frames,
+        [
+            (
+                Some("inner"),
+                vec!["x = 2".to_string(), "y = 4".to_string()]
+            ),
+            (
+                Some("outer"),
+                vec!["a = 1".to_string(), "b = 2".to_string()]
+            ),
+            (None, vec!["top = 1".to_string()]),
+        ]
+    );
+    assert_eq!(err.frames[0].line, Some(3));
+    assert_eq!(err.frames[1].line, Some(7));
+    assert_eq!(err.frames[2].line, Some(10));
+}
+
+#[test]
+fn frames_are_only_captured_on_request() {
+    let (_, result) = run("fn f() { throw 1; } f()", &RunOptions::default());
+    assert!(result.unwrap_err().frames.is_empty());
+}
+
+#[test]
+fn replays_stop_after_the_recorded_operations() {
+    // Record a failure inside a function...
+    let recorder = Rc::new(RefCell::new(PostMortemRecorder::new()));
+    let mut engine = Engine::new();
+    #[allow(deprecated)]
+    engine.register_debugger(|_, debugger| debugger, {
+        let recorder = recorder.clone();
+        move |context, event, _node, _source, _pos| {
+            recorder
+                .borrow_mut()
+                .on_debugger_event(context.global_runtime_state(), &event);
+            Ok(DebuggerCommand::StepInto)
+        }
+    });
+    let ast = engine
+        .compile("fn f() { throw 1; } let a = 1; f()")
+        .unwrap();
+    assert!(engine.run_ast(&ast).is_err());
+
+    // ...and replay a script which never gets there, as if the script was not
+    // deterministic. The replay must still end.
+    let never_calls = Engine::new()
+        .compile("let a = 1; loop { a += 1; }")
+        .unwrap();
+    let frames = recorder.borrow().replay(Engine::new(), &never_calls);
+    assert!(frames.iter().all(|frame| frame.fn_name.is_none()));
+}
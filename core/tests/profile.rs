@@ -0,0 +1,64 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::RunOptions;
+
+const SCRIPT: &str = "fn fib(n) { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }
+fn twice(x) { x * 2 }
+twice(fib(10)) + len([1, 2])";
+
+#[test]
+fn counts_calls_of_script_defined_functions() {
+    let options = RunOptions {
+        profile: true,
+        ..Default::default()
+    };
+    let (_, result) = run(SCRIPT, &options);
+    let result = result.unwrap();
+    assert_eq!(result.result, "112");
+    let profile = result.profile.unwrap();
+
+    let mut calls: Vec<_> = profile
+        .functions
+        .iter()
+        .map(|f| (f.name.as_str(), f.arity, f.calls))
+        .collect();
+    calls.sort();
+    // Native functions like `len` are not reported.
+    assert_eq!(calls, [("fib", 1, 177), ("twice", 1, 1)]);
+    for f in &profile.functions {
+        assert!(f.operations > 0);
+        assert!(f.exclusive_msec <= f.inclusive_msec + 1e-9, "{:?}", f);
+    }
+    assert!(profile
+        .functions
+        .windows(2)
+        .all(|w| w[0].exclusive_msec >= w[1].exclusive_msec));
+    assert!(profile.collapsed_stacks.is_none());
+}
+
+#[test]
+fn outputs_collapsed_stacks_on_request() {
+    let options = RunOptions {
+        profile: true,
+        collapsed_stacks: true,
+        ..Default::default()
+    };
+    let (_, result) = run(SCRIPT, &options);
+    let stacks = result.unwrap().profile.unwrap().collapsed_stacks.unwrap();
+    let mut names: Vec<_> = stacks
+        .lines()
+        .map(|line| line.rsplit_once(' ').unwrap().0)
+        .collect();
+    names.sort();
+    names.dedup();
+    assert!(names.contains(&"<script>"), "{}", stacks);
+    assert!(names.contains(&"<script>;fib;fib;fib"), "{}", stacks);
+    assert!(names.contains(&"<script>;twice"), "{}", stacks);
+}
+
+#[test]
+fn profiles_only_on_request() {
+    let (_, result) = run(SCRIPT, &RunOptions::default());
+    assert!(result.unwrap().profile.is_none());
+}
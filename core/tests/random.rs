@@ -0,0 +1,52 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::random::Rng;
+use rhai_playground_core::RunOptions;
+use std::collections::HashSet;
+
+const SCRIPT: &str = "let a = [1, 2, 3, 4, 5, 6, 7, 8];
+a.shuffle();
+[rand(), rand(1, 6), rand_float(), sample(a), a]";
+
+fn run_with_seed(seed: Option<u64>) -> String {
+    let options = RunOptions {
+        random_seed: seed,
+        ..Default::default()
+    };
+    run(SCRIPT, &options).1.unwrap().result
+}
+
+#[test]
+fn the_same_seed_reproduces_the_numbers() {
+    assert_eq!(run_with_seed(Some(42)), run_with_seed(Some(42)));
+    assert_ne!(run_with_seed(Some(42)), run_with_seed(Some(43)));
+}
+
+#[test]
+fn runs_without_a_seed_differ() {
+    assert_ne!(run_with_seed(None), run_with_seed(None));
+    let seeds: HashSet<_> = (0..1000).map(|_| Rng::entropy_seed()).collect();
+    assert_eq!(seeds.len(), 1000);
+}
+
+#[test]
+fn seed_restarts_the_numbers() {
+    let (_, result) = run(
+        "seed(7); let a = [rand(), rand()]; seed(7); a == [rand(), rand()]",
+        &RunOptions::default(),
+    );
+    assert_eq!(result.unwrap().result, "true");
+}
+
+#[test]
+fn numbers_stay_in_range() {
+    let mut rng = Rng::new(0);
+    for n in 1..100 {
+        assert!(rng.below(n) < n);
+        let x = rng.next_f64();
+        assert!((0.0..1.0).contains(&x));
+    }
+    let (_, result) = run("rand(3, 1)", &RunOptions::default());
+    assert!(result.unwrap_err().message.contains("Invalid range"));
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::inspect::ValueTree;
+use rhai_playground_core::RunOptions;
+
+fn inspect_options() -> RunOptions {
+    RunOptions {
+        inspect_scope: true,
+        ..Default::default()
+    }
+}
+
+/// Formats a value tree as `key: type = value` lines, indented by depth.
+fn format_tree(tree: &ValueTree, indent: usize, lines: &mut Vec<String>) {
+    let key = tree
+        .key
+        .as_deref()
+        .map_or(String::new(), |key| format!("{}: ", key));
+    lines.push(format!(
+        "{:indent$}{}{} = {}",
+        "",
+        key,
+        tree.type_name,
+        tree.value,
+        indent = indent
+    ));
+    for child in &tree.children {
+        format_tree(child, indent + 2, lines);
+    }
+}
+
+#[test]
+fn returns_the_top_level_variables_in_definition_order() {
+    let script = r#"let a = 1;
+const B = [true, #{ x: "s" }];
+fn f() { let local = 0; }
+f();
+let a = "shadowed";"#;
+    let (_, result) = run(script, &inspect_options());
+    let scope = result.unwrap().scope.unwrap();
+    let variables: Vec<_> = scope
+        .iter()
+        .map(|v| (v.name.as_str(), v.is_constant, v.value.value.as_str()))
+        .collect();
+    assert_eq!(
+        variables,
+        // Rhai reuses the variable of the scope given to the run when it is
+        // defined again at the top level.
+        [("a", false, "\"shadowed\""), ("B", true, "[2 items]")]
+    );
+
+    let mut lines = Vec::new();
+    format_tree(&scope[1].value, 0, &mut lines);
+    assert_eq!(
+        lines,
+        [
+            "array = [2 items]",
+            "  0: bool = true",
+            "  1: map = #{1 properties}",
+            "    x: string = \"s\"",
+        ]
+    );
+}
+
+#[test]
+fn shows_the_captured_variables_of_closures() {
+    let (_, result) = run("let x = 2; let f = |y| x + y;", &inspect_options());
+    let scope = result.unwrap().scope.unwrap();
+    let f = &scope[1].value;
+    assert_eq!(f.type_name, "Fn");
+    assert!(f.value.starts_with("Fn(anon$"), "{}", f.value);
+    assert_eq!(f.children[0].key.as_deref(), Some("curry[0]"));
+    assert_eq!(f.children[0].value, "2");
+}
+
+#[test]
+fn inspects_only_on_request() {
+    let (_, result) = run("let a = 1;", &RunOptions::default());
+    assert!(result.unwrap().scope.is_none());
+}
@@ -0,0 +1,123 @@
+use rhai_playground_core::semantic::{semantic_tokens, SemanticToken};
+
+/// Returns the classified identifiers of `script` as `line:text type
+/// modifiers...`.
+fn classify(script: &str) -> Vec<String> {
+    let lines: Vec<_> = script.split('\n').collect();
+    semantic_tokens(script)
+        .unwrap()
+        .into_iter()
+        .map(|token: SemanticToken| {
+            let text = &lines[token.line - 1][token.start..token.end];
+            let mut s = format!("{}:{} {}", token.line, text, token.token_type);
+            for modifier in token.modifiers {
+                s.push(' ');
+                s.push_str(modifier);
+            }
+            s
+        })
+        .collect()
+}
+
+#[test]
+fn classifies_functions_and_parameters() {
+    let script = "fn add(a, b) { a + b }\nlet x = add(1, 2);\nprint(x);";
+    assert_eq!(
+        classify(script),
+        [
+            "1:add function declaration",
+            "1:a parameter declaration",
+            "1:b parameter declaration",
+            "1:a parameter",
+            "1:b parameter",
+            "2:x variable declaration",
+            "2:add function",
+            "3:print function defaultLibrary",
+            "3:x variable",
+        ]
+    );
+}
+
+#[test]
+fn classifies_constants_modules_and_properties() {
+    let script = "import \"m\" as m;\nconst K = #{ foo: 1 };\nK.foo + m::bar(K.len())";
+    assert_eq!(
+        classify(script),
+        [
+            "1:m namespace declaration",
+            "2:K variable declaration readonly",
+            "2:foo property declaration",
+            "3:K variable readonly",
+            "3:foo property",
+            "3:m namespace",
+            "3:bar function",
+            "3:K variable readonly",
+            "3:len method",
+        ]
+    );
+}
+
+#[test]
+fn classifies_closure_parameters_and_captured_variables() {
+    let script = "let y = 1;\nlet f = |p, q| p + q + y;\nf.call(1, 2) + f(1, 2)";
+    assert_eq!(
+        classify(script),
+        [
+            "1:y variable declaration",
+            "2:f variable declaration",
+            "2:p parameter declaration",
+            "2:q parameter declaration",
+            "2:p parameter",
+            "2:q parameter",
+            "2:y variable captured",
+            "3:f variable",
+            "3:call method",
+            "3:f function",
+        ]
+    );
+}
+
+#[test]
+fn classifies_loop_and_catch_variables() {
+    let script =
+        "for (item, i) in [1] { item }\ntry { throw 1 } catch (err) { err }\nlet a = 1 | 2;";
+    assert_eq!(
+        classify(script),
+        [
+            "1:item variable declaration",
+            "1:i variable declaration",
+            "1:item variable",
+            "2:err variable declaration",
+            "2:err variable",
+            "3:a variable declaration",
+        ]
+    );
+}
+
+#[test]
+fn returns_parse_errors() {
+    assert!(semantic_tokens("let = ;").is_err());
+}
+
+#[test]
+fn tokens_of_example_scripts_are_identifiers() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../example-scripts");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let script = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = script.split('\n').collect();
+        let Ok(tokens) = semantic_tokens(&script) else {
+            continue;
+        };
+        for token in tokens {
+            let text = &lines[token.line - 1][token.start..token.end];
+            assert!(
+                text.chars().all(|c| c.is_alphanumeric() || c == '_'),
+                "{}:{}: {:?} is not an identifier",
+                path.display(),
+                token.line,
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use common::{run, run_on};
+use rhai_playground_core::{Playground, RunOptions};
+use std::time::{Duration, Instant};
+
+fn busy_sleep_playground() -> Playground {
+    let mut playground = Playground::new();
+    playground.set_busy_sleep(true);
+    playground
+}
+
+#[test]
+fn fails_without_a_way_to_sleep() {
+    let (output, result) = run("print(1); sleep(50); 2", &RunOptions::default());
+    assert!(result.unwrap_err().message.contains("not supported"));
+    assert_eq!(output, ["[PRINT] 1"]);
+}
+
+#[test]
+fn busy_sleeps_without_a_pause_control() {
+    let start = Instant::now();
+    let (output, result) = run_on(
+        &mut busy_sleep_playground(),
+        "print(1); sleep(50); 2",
+        &RunOptions::default(),
+    );
+    assert_eq!(result.unwrap().result, "2");
+    assert_eq!(output, ["[PRINT] 1"]);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn sleeping_counts_towards_the_time_limit() {
+    let options = RunOptions {
+        max_duration_msec: Some(100),
+        ..Default::default()
+    };
+    let start = Instant::now();
+    let (_, result) = run_on(&mut busy_sleep_playground(), "sleep(10000)", &options);
+    assert!(result.unwrap_err().is_timeout);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
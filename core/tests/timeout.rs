@@ -0,0 +1,45 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::RunOptions;
+use std::time::Instant;
+
+fn time_limit(msec: u64) -> RunOptions {
+    RunOptions {
+        max_duration_msec: Some(msec),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn terminates_scripts_running_too_long() {
+    let start = Instant::now();
+    let (output, result) = run("print(1); loop {}", &time_limit(200));
+    let err = result.unwrap_err();
+    assert!(err.is_timeout);
+    assert_eq!(
+        err.message,
+        "Script timed out: exceeded the time limit of 200 ms"
+    );
+    // The output printed before the timeout is kept.
+    assert_eq!(output, ["[PRINT] 1"]);
+    assert!(start.elapsed().as_millis() < 5000);
+}
+
+#[test]
+fn terminates_instrumented_runs_too() {
+    let options = RunOptions {
+        coverage: true,
+        ..time_limit(200)
+    };
+    let (_, result) = run("let x = 0; loop { x += 1; }", &options);
+    assert!(result.unwrap_err().is_timeout);
+}
+
+#[test]
+fn other_errors_are_not_timeouts() {
+    let (_, result) = run("throw 1", &time_limit(10_000));
+    assert!(!result.unwrap_err().is_timeout);
+    let (_, result) = run("40 + 2", &time_limit(10_000));
+    assert_eq!(result.unwrap().result, "42");
+}
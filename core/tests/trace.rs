@@ -0,0 +1,82 @@
+mod common;
+
+use common::run;
+use rhai_playground_core::trace::TraceEvent;
+use rhai_playground_core::RunOptions;
+
+fn trace_options() -> RunOptions {
+    RunOptions {
+        trace: true,
+        ..Default::default()
+    }
+}
+
+/// Returns the statements of the trace as `line:kind`.
+fn statements(events: &[TraceEvent]) -> Vec<String> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            TraceEvent::Statement { line, kind, .. } => Some(format!("{}:{}", line.unwrap(), kind)),
+            TraceEvent::Variable { .. } => None,
+        })
+        .collect()
+}
+
+#[test]
+fn records_expression_statements() {
+    let script = "let a = [];\na.push(1);\nfn f(x) {\n    x.len()\n}\nf(a);\na.len()";
+    let (_, result) = run(script, &trace_options());
+    let trace = result.unwrap().trace.unwrap();
+    assert_eq!(
+        statements(&trace.events),
+        [
+            "1:let",
+            "2:expression",
+            "6:call",
+            "4:expression",
+            "7:expression"
+        ]
+    );
+    assert!(!trace.is_truncated);
+}
+
+#[test]
+fn records_variable_definitions_and_assignments() {
+    let (_, result) = run("let x = 1;\nx += 2;\nx", &trace_options());
+    let variables: Vec<_> = result
+        .unwrap()
+        .trace
+        .unwrap()
+        .events
+        .into_iter()
+        .filter_map(|event| match event {
+            TraceEvent::Variable {
+                line,
+                name,
+                is_definition,
+                value,
+                ..
+            } => Some((line.unwrap(), name, is_definition, value)),
+            TraceEvent::Statement { .. } => None,
+        })
+        .collect();
+    assert_eq!(
+        variables,
+        [
+            (1, "x".to_string(), true, "1".to_string()),
+            (2, "x".to_string(), false, "3".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn stops_at_the_event_cap() {
+    let options = RunOptions {
+        max_trace_events: Some(3),
+        ..trace_options()
+    };
+    let (_, result) = run("let x = 0;\nwhile x < 10 { x += 1; }", &options);
+    let trace = result.unwrap().trace.unwrap();
+    assert_eq!(trace.events.len(), 3);
+    assert!(trace.is_truncated);
+}
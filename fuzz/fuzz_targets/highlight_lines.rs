@@ -0,0 +1,19 @@
+//! Highlights arbitrary text line by line, like the editor does, and checks
+//! that every token reads at least one character.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rhai_playground_core::highlight::{indent, token, CharStream, LineStream, State};
+
+fuzz_target!(|text: &str| {
+    let mut state = State::default();
+    for line in text.split('\n') {
+        let mut stream = LineStream::new(line);
+        while !stream.eol() {
+            let start = stream.pos();
+            token(&mut stream, &mut state);
+            assert!(stream.pos() > start, "no progress in {:?}", line);
+        }
+        let _ = indent(&state, 4, line);
+    }
+});
@@ -0,0 +1,24 @@
+//! Highlights arbitrary UTF-16 text, including unpaired surrogates, line by
+//! line, like the editor does: the text is converted to a `String` like the
+//! wasm bindings do, and the offsets of the tokens are converted back to
+//! UTF-16. Checks that the tokens are not empty and end at the end of the
+//! line.
+
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rhai_playground_core::highlight::{to_utf16_offsets, tokenize_line, LineStream, State};
+
+fuzz_target!(|units: Vec<u16>| {
+    let mut state = State::default();
+    for units in units.split(|&unit| unit == u16::from(b'\n')) {
+        let line = String::from_utf16_lossy(units);
+        let mut tokens = tokenize_line(&mut LineStream::new(&line), &mut state);
+        to_utf16_offsets(&line, &mut tokens);
+        let mut start = 0;
+        for token in &tokens {
+            assert!(token.end > start, "empty token in {:?}", line);
+            start = token.end;
+        }
+        assert_eq!(start, units.len(), "tokens do not cover {:?}", line);
+    }
+});
@@ -0,0 +1,62 @@
+// Rhai language support for CodeMirror 6, using the same tokenizer and
+// indentation as the CodeMirror 5 mode of the playground. The playground itself
+// still uses CodeMirror 5, so `@codemirror/language` must be installed by the
+// application using this file.
+//
+//     import { wasmImport } from "./wasm_loader.js";
+//     import { rhai } from "./codemirror6-rhai.js";
+//
+//     const wasm = await wasmImport;
+//     new EditorView({ extensions: [basicSetup, rhai(wasm)], parent });
+
+import { foldService, LanguageSupport, StreamLanguage } from "@codemirror/language";
+
+/**
+ * @param {object} wasm The loaded WASM module.
+ * @param {number} [indentUnit] Number of columns of one level of indentation.
+ * @returns {LanguageSupport}
+ */
+function rhai(wasm, indentUnit = 4) {
+    const mode = new wasm.RhaiMode(indentUnit);
+
+    const rhaiLanguage = StreamLanguage.define({
+        name: "rhai",
+        startState: () => mode.startState(),
+        copyState: state => mode.copyState(state),
+        token: (stream, state) => mode.token(stream, state),
+        indent(state, textAfter) {
+            // The mode returns `CodeMirror.Pass`, which is not set here, when
+            // it does not know the indentation.
+            const indent = mode.indent(state, textAfter);
+            return typeof indent === "number" ? indent : null;
+        },
+        languageData: {
+            commentTokens: { line: "//", block: { open: "/*", close: "*/" } },
+            indentOnInput: /^\s*[}\])]$/,
+        },
+    });
+
+    // The ranges are computed for the whole document at once, and only again
+    // once it has changed.
+    const foldRangesCache = new WeakMap();
+    const rhaiFolding = foldService.of((state, lineStart, lineEnd) => {
+        let ranges = foldRangesCache.get(state.doc);
+        if (!ranges) {
+            ranges = wasm.foldRanges(state.doc.toString());
+            foldRangesCache.set(state.doc, ranges);
+        }
+        for (let i = 0; i < ranges.length; i += 2) {
+            if (ranges[i] > lineEnd) {
+                break;
+            }
+            if (ranges[i] >= lineStart) {
+                return { from: ranges[i], to: ranges[i + 1] };
+            }
+        }
+        return null;
+    });
+
+    return new LanguageSupport(rhaiLanguage, [rhaiFolding]);
+}
+
+export { rhai };
@@ -0,0 +1,20 @@
+// Layout of the `Int32Array` shared between the page and the worker, which
+// must match the constants in `src/playground.rs`.
+
+/** Index of the pause flag. */
+export const CONTROL_PAUSE = 0;
+export const PAUSE_REQUESTED = 1;
+
+/** Index of the state of an `input()` request. */
+export const CONTROL_INPUT = 1;
+export const INPUT_WAITING = 1;
+export const INPUT_READY = 2;
+export const INPUT_END = 3;
+/** Index of the length in bytes of the line written to the input buffer. */
+export const CONTROL_INPUT_LEN = 2;
+
+/** Size in bytes of the buffer holding the line for `input()`. */
+export const INPUT_BUFFER_LEN = 64 * 1024;
+
+/** Number of elements in the control array. */
+export const CONTROL_LEN = 3;
@@ -3,6 +3,46 @@
 //        to worker-loader once the issue is fixed.
 //        Blocked on: https://github.com/webpack-contrib/worker-loader/pull/175
 import MyWorker from "workerize-loader!./worker.js";
+import {
+    CONTROL_INPUT,
+    CONTROL_INPUT_LEN,
+    CONTROL_LEN,
+    CONTROL_PAUSE,
+    INPUT_BUFFER_LEN,
+    INPUT_END,
+    INPUT_READY,
+    PAUSE_REQUESTED,
+} from "./control.js";
+
+const isSharedMemoryAvailable = typeof SharedArrayBuffer !== "undefined" && self.crossOriginIsolated;
+
+/**
+ * Shared with the worker to pause scripts and answer `input()` calls. Shared
+ * memory is only available when the page is cross-origin isolated.
+ * @type Int32Array?
+ */
+const control = isSharedMemoryAvailable
+    ? new Int32Array(new SharedArrayBuffer(CONTROL_LEN * Int32Array.BYTES_PER_ELEMENT))
+    : null;
+
+/**
+ * Holds the line entered for `input()` as UTF-8.
+ * @type Uint8Array?
+ */
+const inputBuffer = isSharedMemoryAvailable
+    ? new Uint8Array(new SharedArrayBuffer(INPUT_BUFFER_LEN))
+    : null;
+
+function answerInput(line) {
+    if (line === null) {
+        Atomics.store(control, CONTROL_INPUT, INPUT_END);
+    } else {
+        const { written } = new TextEncoder().encodeInto(line, inputBuffer);
+        Atomics.store(control, CONTROL_INPUT_LEN, written);
+        Atomics.store(control, CONTROL_INPUT, INPUT_READY);
+    }
+    Atomics.notify(control, CONTROL_INPUT);
+}
 
 let workerLoader = (function () {
     /**
@@ -99,9 +139,11 @@ let runScriptPromiseReject = null;
  * @param {string} script
  * @param {AppendOutputCallback} appendOutput
  * @param {(Number) => void} updateOps
- * @returns {Promise<void>}
+ * @param {object} [options] Run options passed to `Playground.runScript`.
+ * @returns {Promise<{ coverage?: object, trace?: object, scope?: object[] }>}
+ *          Extra results of the run.
  */
-function runScript(script, appendOutput, updateOps) {
+function runScript(script, appendOutput, updateOps, options) {
     if (runScriptMessageListener) {
         return Promise.reject("Another script is running.");
     }
@@ -118,19 +160,54 @@ function runScript(script, appendOutput, updateOps) {
                     worker.removeEventListener("message", runScriptMessageListener);
                     runScriptMessageListener = null;
                     runScriptPromiseReject = null;
-                    resolve();
+                    resolve({
+                        coverage: ev.data.coverage,
+                        trace: ev.data.trace,
+                        scope: ev.data.scope,
+                    });
                 } else if (ev.data.req === "runScript/updateOps") {
                     updateOps(ev.data.ops);
+                } else if (ev.data.req === "runScript/paused") {
+                    const info = ev.data.info;
+                    const at = info.line ? ` at line ${info.line}, position ${info.column}` : "";
+                    appendOutput(`Paused${at} after ${info.operations} operations`);
+                    updateOps(info.operations);
+                } else if (ev.data.req === "runScript/input") {
+                    // Let the output be shown before the prompt blocks the page.
+                    setTimeout(() => answerInput(prompt(ev.data.prompt || "Input for the script:")), 50);
                 }
             })
             runScriptPromiseReject = reject;
-            worker.postMessage({ req: "runScript", script });
+            if (control) {
+                Atomics.store(control, CONTROL_PAUSE, 0);
+                Atomics.store(control, CONTROL_INPUT, 0);
+            }
+            worker.postMessage({ req: "runScript", script, options, control, inputBuffer });
         }).catch(e => {
             reject("Cannot load Worker: " + e);
         });
     });
 }
 
+function isPauseSupported() {
+    return control !== null;
+}
+
+function pauseScript() {
+    if (control) {
+        Atomics.store(control, CONTROL_PAUSE, PAUSE_REQUESTED);
+        // Wakes up the worker if the script is in `sleep()`.
+        Atomics.notify(control, CONTROL_PAUSE);
+    }
+}
+
+function resumeScript() {
+    if (control) {
+        Atomics.store(control, CONTROL_PAUSE, 0);
+        Atomics.notify(control, CONTROL_PAUSE);
+    }
+}
+
 function stopScript() {
     workerLoader.terminateWorker();
     if (runScriptPromiseReject) {
@@ -140,4 +217,4 @@ function stopScript() {
     }
 }
 
-export { runScript, stopScript };
+export { runScript, stopScript, isPauseSupported, pauseScript, resumeScript };
@@ -2,37 +2,96 @@ import { wasmImport } from "./wasm_loader.js";
 
 const playgroundPromise = wasmImport.then(wasm => new wasm.Playground);
 
-async function runScript(script) {
+function formatProfile(profile) {
+    const lines = ["\nProfile (exclusive msec / inclusive msec / calls / ops):"];
+    for (const f of profile.functions) {
+        lines.push(`  ${f.name}/${f.arity}: ${f.exclusiveMsec.toFixed(3)} / ${f.inclusiveMsec.toFixed(3)} / ${f.calls} / ${f.operations}`);
+    }
+    if (profile.collapsedStacks) {
+        lines.push("\nCollapsed stacks:", profile.collapsedStacks);
+    }
+    return lines.join("\n");
+}
+
+function formatFrames(frames) {
+    const lines = ["\nCall frames at the point of failure:"];
+    for (const frame of frames) {
+        lines.push(`  ${frame.fnName || "<script>"} (line ${frame.line}, position ${frame.column})`);
+        for (const v of frame.variables) {
+            lines.push(`    ${v.isConstant ? "const " : ""}${v.name} = ${v.value}`);
+        }
+    }
+    return lines.join("\n");
+}
+
+async function runScript(script, options, control, inputBuffer) {
     const playground = await playgroundPromise;
+    if (control) {
+        playground.enablePause(control, info => {
+            self.postMessage({
+                req: "runScript/paused",
+                info,
+            });
+        });
+        playground.enableInput(control, inputBuffer, prompt => {
+            self.postMessage({
+                req: "runScript/input",
+                prompt,
+            });
+        });
+    } else {
+        playground.enableBusySleep();
+    }
     function output(line) {
         self.postMessage({
             req: "runScript/output",
             output: line,
         });
     }
+    let coverage, trace, scope;
     try {
-        let result = playground.runScript(script, s => {
-            output(`[PRINT] ${s}`);
-        }, s => {
-            output(`[DEBUG] ${s}`);
-        }, ops => {
+        let result = playground.runScript(script, output, ops => {
             self.postMessage({
                 req: "runScript/updateOps",
                 ops,
             });
-        });
-        output(`\nScript returned: "${result}"`);
+        }, options);
+        if (result.profile) {
+            output(formatProfile(result.profile));
+        }
+        if (result.alloc) {
+            const a = result.alloc;
+            output(`\nAllocations: ${a.allocations}, frees: ${a.frees}, bytes in use: ${a.startBytes} -> ${a.endBytes} (peak ${a.peakBytes})`);
+        }
+        coverage = result.coverage;
+        trace = result.trace;
+        scope = result.scope;
+        if (options && options.jsonResult) {
+            output(`\nScript returned:\n${result.result}`);
+        } else {
+            output(`\nScript returned: "${result.result}"`);
+        }
     } catch (ex) {
-        output(`\nEXCEPTION: ${ex}`);
+        if (ex && ex.message !== undefined) {
+            output(`\nEXCEPTION: ${ex.message}`);
+            if (ex.frames) {
+                output(formatFrames(ex.frames));
+            }
+        } else {
+            output(`\nEXCEPTION: ${ex}`);
+        }
     }
     postMessage({
         req: "runScript/end",
+        coverage,
+        trace,
+        scope,
     });
 }
 
 self.onmessage = ev => {
     if (ev.data.req === "runScript") {
-        runScript(ev.data.script);
+        runScript(ev.data.script, ev.data.options, ev.data.control, ev.data.inputBuffer);
     } else {
         console.log("Unknown message received by worker:", ev.data);
     }
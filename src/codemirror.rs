@@ -1,10 +1,25 @@
 use js_sys::{Array, JsString, RegExp};
 use wasm_bindgen::prelude::*;
 
+// The methods used by `RhaiMode` are also in the `StringStream` of CodeMirror
+// 6, so the mode works with both versions. (See `js/codemirror6-rhai.js`.)
 #[wasm_bindgen(module = "codemirror")]
 extern "C" {
     pub type StringStream;
 
+    /// The whole line.
+    #[wasm_bindgen(method, getter)]
+    pub fn string(this: &StringStream) -> String;
+
+    #[wasm_bindgen(method, getter)]
+    pub fn pos(this: &StringStream) -> u32;
+
+    #[wasm_bindgen(method, setter)]
+    pub fn set_pos(this: &StringStream, pos: u32);
+
+    #[wasm_bindgen(method, getter, js_name = tabSize)]
+    pub fn tab_size(this: &StringStream) -> u32;
+
     #[wasm_bindgen(method)]
     #[must_use]
     pub fn eol(this: &StringStream) -> bool;
@@ -1,46 +1,94 @@
+use rhai::ParseError;
+#[cfg(feature = "track_alloc")]
+use rhai_playground_core::alloc_tracker;
+use rhai_playground_core::{scripting, semantic};
 use wasm_bindgen::prelude::*;
+use web_sys::console;
 
 mod cm_rhai_mode;
 mod codemirror;
+mod monaco;
 mod playground;
-mod scripting;
 
-#[wasm_bindgen]
-pub fn run_script(
-    script: String,
-    print_callback: js_sys::Function,
-    debug_callback: js_sys::Function,
-    progress_callback: Option<js_sys::Function>,
-) -> Result<String, JsValue> {
-    Ok(scripting::run_script(
-        &script,
-        move |s| {
-            let _ = print_callback.call1(&JsValue::null(), &JsValue::from_str(s));
-        },
-        move |s| {
-            let _ = debug_callback.call1(&JsValue::null(), &JsValue::from_str(s));
-        },
-        move |ops| {
-            if let Some(f) = &progress_callback {
-                let _ = f.call1(&JsValue::null(), &JsValue::from_f64(ops as f64));
-            }
-        },
-    )?)
-}
+// The Rust API, which is also available without the wasm bindings from the
+// `rhai-playground-core` crate.
+pub use rhai_playground_core::{
+    dump_ast, DateTime, InputProvider, PauseControl, PauseInfo, Playground, RunError, RunOptions,
+    RunResult,
+};
 
 #[wasm_bindgen]
 pub fn compile_script(script: String) -> Result<String, JsValue> {
-    Ok(scripting::compile_ast(&script)?)
+    let s = scripting::dump_ast(&script).map_err(parse_error_to_js)?;
+    console::log_1(&JsValue::from_str("Script compiled to AST!"));
+    Ok(s)
+}
+
+/// Parses an ISO-8601 date or time like `parse_date()` in scripts and the
+/// `--fixed-time` option of the command line, taking times without an offset
+/// as UTC. Returns the milliseconds since the Unix epoch, or `undefined` if
+/// the string is invalid.
+#[wasm_bindgen(js_name = parseIsoDate)]
+pub fn parse_iso_date(s: &str) -> Option<f64> {
+    DateTime::parse_iso(s).map(|dt| dt.epoch_msec() as f64)
+}
+
+/// Returns the classified identifiers of the script as an array of
+/// `{ line, start, end, type, modifiers }`, with 1-based lines and UTF-16
+/// offsets in the lines, or the parse error.
+#[wasm_bindgen(js_name = semanticTokens)]
+pub fn semantic_tokens(script: &str) -> Result<JsValue, JsValue> {
+    let mut tokens = semantic::semantic_tokens(script).map_err(parse_error_to_js)?;
+    let lines: Vec<_> = script.split('\n').collect();
+    for token in &mut tokens {
+        let line = lines[token.line - 1];
+        let start = line[..token.start].encode_utf16().count();
+        token.end = start + line[token.start..token.end].encode_utf16().count();
+        token.start = start;
+    }
+    #[allow(deprecated)]
+    Ok(JsValue::from_serde(&tokens).unwrap())
+}
+
+#[derive(serde::Serialize)]
+struct OutParseError {
+    message: String,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+fn parse_error_to_js(e: ParseError) -> JsValue {
+    let ParseError(err, pos) = e;
+    let res = OutParseError {
+        message: err.to_string(),
+        line: pos.line().map(|x| x as u32),
+        column: pos.position().map(|x| x as u32),
+    };
+    #[allow(deprecated)]
+    JsValue::from_serde(&res).unwrap()
 }
 
 // When the `wee_alloc` feature is enabled, this uses `wee_alloc` as the global
 // allocator.
 //
 // If you don't want to use `wee_alloc`, you can safely delete this.
-#[cfg(feature = "wee_alloc")]
+#[cfg(all(feature = "wee_alloc", not(feature = "track_alloc")))]
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
+// When the `track_alloc` feature is enabled, the global allocator (`wee_alloc`
+// or the default one) is wrapped to collect the figures reported in the
+// results of `Playground::run_script`.
+#[cfg(all(feature = "track_alloc", feature = "wee_alloc"))]
+#[global_allocator]
+static ALLOC: alloc_tracker::TrackingAllocator<wee_alloc::WeeAlloc> =
+    alloc_tracker::TrackingAllocator::new(wee_alloc::WeeAlloc::INIT);
+
+#[cfg(all(feature = "track_alloc", not(feature = "wee_alloc")))]
+#[global_allocator]
+static ALLOC: alloc_tracker::TrackingAllocator<std::alloc::System> =
+    alloc_tracker::TrackingAllocator::new(std::alloc::System);
+
 // This is like the `main` function, except for JavaScript.
 #[wasm_bindgen(start)]
 pub fn main_js() -> Result<(), JsValue> {
@@ -0,0 +1,84 @@
+//! A tokens provider for the Monaco editor, which highlights Rhai like the
+//! CodeMirror mode does. Register it with:
+//!
+//! ```js
+//! monaco.languages.register({ id: "rhai" });
+//! monaco.languages.setTokensProvider("rhai", new wasm.RhaiTokensProvider());
+//! ```
+
+use js_sys::{Array, Object, Reflect};
+use rhai_playground_core::highlight::{self, LineStream};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct RhaiTokensProvider {}
+
+/// The state at the start of a line, which is `IState` in Monaco.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct MonacoState(highlight::State);
+
+#[wasm_bindgen]
+impl MonacoState {
+    #[wasm_bindgen(js_name = clone)]
+    pub fn clone_state(&self) -> MonacoState {
+        self.clone()
+    }
+
+    pub fn equals(&self, other: &MonacoState) -> bool {
+        self.0 == other.0
+    }
+}
+
+#[wasm_bindgen]
+impl RhaiTokensProvider {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    #[wasm_bindgen(js_name = getInitialState)]
+    pub fn get_initial_state(&self) -> MonacoState {
+        MonacoState(highlight::State::default())
+    }
+
+    /// Returns an `ILineTokens` object with the `startIndex` and `scopes` of
+    /// each token, and the state at the end of the line as `endState`.
+    pub fn tokenize(&self, line: String, state: &MonacoState) -> Result<JsValue, JsValue> {
+        let mut end_state = state.clone();
+        let mut tokens = highlight::tokenize_line(&mut LineStream::new(&line), &mut end_state.0);
+        highlight::to_utf16_offsets(&line, &mut tokens);
+
+        let monaco_tokens = Array::new();
+        let mut start = 0;
+        for token in tokens {
+            let monaco_token = Object::new();
+            Reflect::set(&monaco_token, &"startIndex".into(), &(start as u32).into())?;
+            Reflect::set(&monaco_token, &"scopes".into(), &scope(token.style).into())?;
+            monaco_tokens.push(&monaco_token);
+            start = token.end;
+        }
+
+        let result = Object::new();
+        Reflect::set(&result, &"tokens".into(), &monaco_tokens)?;
+        Reflect::set(&result, &"endState".into(), &end_state.into())?;
+        Ok(result.into())
+    }
+}
+
+/// Returns the name of the token in Monaco themes for a style of the
+/// CodeMirror mode.
+fn scope(style: Option<&'static str>) -> &'static str {
+    match style {
+        Some("def") => "variable",
+        Some("variable") => "identifier",
+        Some("string-2") => "string.char",
+        Some("bracket") => "delimiter.bracket",
+        Some("builtin") => "keyword",
+        Some("error") => "invalid",
+        // `keyword`, `number`, `string`, `operator` and `comment` are the
+        // same in Monaco.
+        Some(style) => style,
+        None => "",
+    }
+}
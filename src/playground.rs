@@ -1,81 +1,10 @@
-use instant::Instant;
-use rhai::Engine;
-use std::cell::RefCell;
-use wasm_bindgen::prelude::*;
-
-pub struct Playground {
-    engine: Engine,
-}
-
-impl Playground {
-    pub fn new() -> Self {
-        let mut engine = rhai::Engine::new();
-        engine.disable_symbol("eval");
-        engine.on_print(|_| {});
-        engine.on_debug(|_, _, _| {});
-        Self {
-            engine: Engine::new(),
-        }
-    }
-
-    pub fn run_script(
-        &mut self,
-        script: &str,
-        print_callback: impl Fn(&str) + 'static,
-        debug_callback: impl Fn(&str) + 'static,
-        progress_callback: impl Fn(u64) + 'static,
-    ) -> Result<String, String> {
-        struct Defer<'z> {
-            mut_self: &'z mut Playground,
-        }
-
-        let defer = Defer { mut_self: self };
-        let engine = &mut defer.mut_self.engine;
-
-        engine.on_print(move |s| print_callback(s));
-        engine.on_debug(move |s, src, pos| {
-            debug_callback(&src.map_or_else(
-                || format!("<script>:[{}] {}", pos, s),
-                |src| format!("{}:[{}] {}", src, pos, s),
-            ))
-        });
-        let script_ast = engine.compile(&script).map_err(|e| e.to_string())?;
-
-        let interval = RefCell::new(1000);
-        let last_instant = RefCell::new(Instant::now());
-        engine.on_progress(move |ops| {
-            let interval_value = *interval.borrow();
-            if ops % interval_value == 0 {
-                let mut last_instant = last_instant.borrow_mut();
-                let new_instant = Instant::now();
-                let duration_msec = new_instant.duration_since(*last_instant).as_millis();
-                if duration_msec < 50 {
-                    interval.replace(interval_value * 10);
-                } else if duration_msec >= 100 {
-                    progress_callback(ops);
-                    *last_instant = new_instant;
-                    if duration_msec >= 500 && interval_value > 1 {
-                        interval.replace(interval_value / 10);
-                    }
-                }
-            }
-            None
-        });
+//! The `Playground` class used by the web worker, which wraps the core
+//! `Playground` and connects it to JavaScript callbacks and shared memory.
 
-        let result: rhai::Dynamic = engine.eval_ast(&script_ast).map_err(|e| e.to_string())?;
-
-        return Ok(result.to_string());
-
-        impl Drop for Defer<'_> {
-            fn drop(&mut self) {
-                let engine = &mut self.mut_self.engine;
-                engine.on_print(|_| {});
-                engine.on_debug(|_, _, _| {});
-                engine.on_progress(|_| None);
-            }
-        }
-    }
-}
+use rhai_playground_core::{InputProvider, PauseControl, PauseInfo, Playground, RunOptions};
+use std::rc::Rc;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(js_name = Playground)]
 pub struct PlaygroundExport(Playground);
@@ -87,27 +16,174 @@ impl PlaygroundExport {
         Self(Playground::new())
     }
 
+    /// `options` is an optional object with the fields of `RunOptions` in
+    /// camelCase, e.g. `{ maxOutputLen: 100000 }`. Returns the `RunResult`
+    /// as a plain object.
     #[wasm_bindgen(js_name = runScript)]
     pub fn run_script(
         &mut self,
         script: String,
-        print_callback: js_sys::Function,
-        debug_callback: js_sys::Function,
+        output_callback: js_sys::Function,
         progress_callback: Option<js_sys::Function>,
-    ) -> Result<String, JsValue> {
-        Ok(self.0.run_script(
+        options: JsValue,
+    ) -> Result<JsValue, JsValue> {
+        let options = run_options_from_js(&options)?;
+        let result = self.0.run_script(
             &script,
+            &options,
             move |s| {
-                let _ = print_callback.call1(&JsValue::null(), &JsValue::from_str(s));
-            },
-            move |s| {
-                let _ = debug_callback.call1(&JsValue::null(), &JsValue::from_str(s));
+                let _ = output_callback.call1(&JsValue::null(), &JsValue::from_str(s));
             },
             move |ops| {
                 if let Some(f) = &progress_callback {
                     let _ = f.call1(&JsValue::null(), &JsValue::from_f64(ops as f64));
                 }
             },
-        )?)
+        );
+        #[allow(deprecated)]
+        match result {
+            Ok(result) => Ok(JsValue::from_serde(&result).unwrap()),
+            Err(err) => Err(JsValue::from_serde(&err).unwrap()),
+        }
+    }
+
+    /// Enables pausing scripts through `control`, an `Int32Array` on a
+    /// `SharedArrayBuffer`. Scripts pause once `CONTROL_PAUSE` is set to
+    /// `PAUSE_REQUESTED`, call `pause_callback` with the `PauseInfo` and
+    /// resume once the page resets it and calls `Atomics.notify`. This also
+    /// enables `sleep()`, which blocks with `Atomics.wait`. Must only be used
+    /// in a worker, where `Atomics.wait` is allowed.
+    #[wasm_bindgen(js_name = enablePause)]
+    pub fn enable_pause(&mut self, control: js_sys::Int32Array, pause_callback: js_sys::Function) {
+        self.0.set_pause_control(Some(Rc::new(SharedPauseControl {
+            control,
+            pause_callback,
+        })));
+    }
+
+    /// Lets `sleep()` spin when shared memory for `enable_pause` is not
+    /// available. Must only be used in a worker, as spinning on the main
+    /// thread freezes the page.
+    #[wasm_bindgen(js_name = enableBusySleep)]
+    pub fn enable_busy_sleep(&mut self) {
+        self.0.set_busy_sleep(true);
+    }
+
+    /// Enables `input()` without `RunOptions::input_lines`, using the same
+    /// `control` array as `enable_pause`. To read a line, the script sets
+    /// `CONTROL_INPUT` to `INPUT_WAITING` and calls `input_callback` with the
+    /// prompt. The page then writes the line as UTF-8 into `buffer`, its
+    /// length into `CONTROL_INPUT_LEN` and sets `CONTROL_INPUT` to
+    /// `INPUT_READY`, or to `INPUT_END` if there is no more input, followed by
+    /// `Atomics.notify`. Must only be used in a worker.
+    #[wasm_bindgen(js_name = enableInput)]
+    pub fn enable_input(
+        &mut self,
+        control: js_sys::Int32Array,
+        buffer: js_sys::Uint8Array,
+        input_callback: js_sys::Function,
+    ) {
+        self.0.set_input_provider(Some(Rc::new(SharedInputProvider {
+            control,
+            buffer,
+            input_callback,
+        })));
+    }
+
+    /// Returns the `BenchmarkResult` as a plain object.
+    pub fn benchmark(&mut self, script: String, iterations: u32) -> Result<JsValue, JsValue> {
+        let result = self.0.benchmark(&script, iterations)?;
+        #[allow(deprecated)]
+        Ok(JsValue::from_serde(&result).unwrap())
+    }
+}
+
+fn run_options_from_js(options: &JsValue) -> Result<RunOptions, JsValue> {
+    if options.is_null() || options.is_undefined() {
+        return Ok(RunOptions::default());
+    }
+    #[allow(deprecated)]
+    options
+        .into_serde()
+        .map_err(|e| JsValue::from_str(&format!("Invalid run options: {}", e)))
+}
+
+/// Index of the pause flag in the shared control array.
+const CONTROL_PAUSE: u32 = 0;
+const PAUSE_REQUESTED: i32 = 1;
+/// Index of the state of an input request in the shared control array.
+const CONTROL_INPUT: u32 = 1;
+const INPUT_WAITING: i32 = 1;
+/// Any other state set by the page means the end of the input.
+const INPUT_READY: i32 = 2;
+/// Index of the length in bytes of the entered line.
+const CONTROL_INPUT_LEN: u32 = 2;
+
+struct SharedPauseControl {
+    control: js_sys::Int32Array,
+    pause_callback: js_sys::Function,
+}
+
+impl PauseControl for SharedPauseControl {
+    fn is_pause_requested(&self) -> bool {
+        js_sys::Atomics::load(&self.control, CONTROL_PAUSE) == Ok(PAUSE_REQUESTED)
+    }
+
+    fn wait_for_resume(&self, info: &PauseInfo) {
+        #[allow(deprecated)]
+        let info = JsValue::from_serde(info).unwrap();
+        let _ = self.pause_callback.call1(&JsValue::null(), &info);
+        while self.is_pause_requested() {
+            if js_sys::Atomics::wait(&self.control, CONTROL_PAUSE, PAUSE_REQUESTED).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn sleep(&self, duration: Duration) {
+        // Returns early when the page requests a pause, which stores
+        // `PAUSE_REQUESTED` and calls `Atomics.notify`.
+        let _ = js_sys::Atomics::wait_with_timeout(
+            &self.control,
+            CONTROL_PAUSE,
+            0,
+            duration.as_secs_f64() * 1000.0,
+        );
+    }
+}
+
+struct SharedInputProvider {
+    control: js_sys::Int32Array,
+    buffer: js_sys::Uint8Array,
+    input_callback: js_sys::Function,
+}
+
+impl InputProvider for SharedInputProvider {
+    fn read_line(&self, prompt: &str) -> Option<String> {
+        let _ = js_sys::Atomics::store(&self.control, CONTROL_INPUT, INPUT_WAITING);
+        let _ = self
+            .input_callback
+            .call1(&JsValue::null(), &JsValue::from_str(prompt));
+        let state = loop {
+            match js_sys::Atomics::load(&self.control, CONTROL_INPUT) {
+                Ok(INPUT_WAITING) => {
+                    if js_sys::Atomics::wait(&self.control, CONTROL_INPUT, INPUT_WAITING).is_err() {
+                        return None;
+                    }
+                }
+                Ok(state) => break state,
+                Err(_) => return None,
+            }
+        };
+        let _ = js_sys::Atomics::store(&self.control, CONTROL_INPUT, 0);
+        if state != INPUT_READY {
+            return None;
+        }
+        let len = js_sys::Atomics::load(&self.control, CONTROL_INPUT_LEN).unwrap_or(0) as u32;
+        let bytes = self
+            .buffer
+            .subarray(0, len.min(self.buffer.length()))
+            .to_vec();
+        Some(String::from_utf8_lossy(&bytes).into_owned())
     }
 }
@@ -0,0 +1,59 @@
+// Static hosts such as GitHub Pages cannot set the headers that make a page
+// cross-origin isolated, which is needed for `SharedArrayBuffer`. This script
+// registers itself as a service worker which adds the headers to every
+// response, so that scripts in the worker can be paused, read `input()` and
+// `sleep()` without spinning.
+//
+// The same file is loaded by the page and run as the service worker.
+
+if (typeof window === "undefined") {
+    self.addEventListener("install", () => self.skipWaiting());
+    self.addEventListener("activate", event => event.waitUntil(self.clients.claim()));
+
+    self.addEventListener("fetch", event => {
+        const request = event.request;
+        // Chrome throws on these requests if they are not same-origin.
+        if (request.cache === "only-if-cached" && request.mode !== "same-origin") {
+            return;
+        }
+        event.respondWith(fetch(request).then(response => {
+            // Opaque responses cannot be modified.
+            if (response.status === 0) {
+                return response;
+            }
+            const headers = new Headers(response.headers);
+            headers.set("Cross-Origin-Opener-Policy", "same-origin");
+            headers.set("Cross-Origin-Embedder-Policy", "require-corp");
+            return new Response(response.body, {
+                status: response.status,
+                statusText: response.statusText,
+                headers,
+            });
+        }));
+    });
+} else {
+    (function () {
+        if (window.crossOriginIsolated || !window.isSecureContext || !("serviceWorker" in navigator)) {
+            return;
+        }
+        // Only reload once, in case the headers do not take effect, e.g. in
+        // a browser without support for cross-origin isolation.
+        const reloadedKey = "coiServiceWorkerReloaded";
+        const hasReloaded = sessionStorage.getItem(reloadedKey) !== null;
+        sessionStorage.removeItem(reloadedKey);
+        navigator.serviceWorker.register(document.currentScript.src).then(() => {
+            // The page is already controlled but still not isolated, so
+            // reloading would not help.
+            if (hasReloaded || navigator.serviceWorker.controller) {
+                return;
+            }
+            // Reload once the service worker is active to get the headers.
+            return navigator.serviceWorker.ready.then(() => {
+                sessionStorage.setItem(reloadedKey, "");
+                window.location.reload();
+            });
+        }, err => {
+            console.warn("Failed to register the cross-origin isolation service worker", err);
+        });
+    })();
+}
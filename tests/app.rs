@@ -1,5 +1,4 @@
 use wasm_bindgen_test::{wasm_bindgen_test_configure, wasm_bindgen_test};
-use futures::prelude::*;
 use wasm_bindgen::JsValue;
 use wasm_bindgen_futures::JsFuture;
 
@@ -9,27 +8,30 @@ wasm_bindgen_test_configure!(run_in_browser);
 // This runs a unit test in native Rust, so it can only use Rust APIs.
 #[test]
 fn rust_test() {
-    assert_eq!(1, 1);
+    let mut playground = rhai_playground::Playground::new();
+    let result = playground
+        .run_script("40 + 2", &Default::default(), |_| {}, |_| {})
+        .unwrap();
+    assert_eq!(result.result, "42");
 }
 
 
 // This runs a unit test in the browser, so it can use browser APIs.
 #[wasm_bindgen_test]
 fn web_test() {
-    assert_eq!(1, 1);
+    let ast = rhai_playground::compile_script("let x = 1;".into()).unwrap();
+    assert!(ast.contains("Statements"));
 }
 
 
 // This runs a unit test in the browser, and in addition it supports asynchronous Future APIs.
-#[wasm_bindgen_test(async)]
-fn async_test() -> impl Future<Item = (), Error = JsValue> {
+#[wasm_bindgen_test]
+async fn async_test() {
     // Creates a JavaScript Promise which will asynchronously resolve with the value 42.
     let promise = js_sys::Promise::resolve(&JsValue::from(42));
 
     // Converts that Promise into a Future.
     // The unit test will wait for the Future to resolve.
-    JsFuture::from(promise)
-        .map(|x| {
-            assert_eq!(x, 42);
-        })
+    let x = JsFuture::from(promise).await.unwrap();
+    assert_eq!(x, 42);
 }
@@ -62,6 +62,12 @@ module.exports = {
   },
   devServer: {
     contentBase: dist,
+    // Cross-origin isolation enables `SharedArrayBuffer`, which is needed to
+    // pause scripts running in the worker.
+    headers: {
+      "Cross-Origin-Opener-Policy": "same-origin",
+      "Cross-Origin-Embedder-Policy": "require-corp",
+    },
   },
   module: {
     rules: [{